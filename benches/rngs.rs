@@ -6,7 +6,7 @@ extern crate rand_xoshiro;
 extern crate criterion;
 
 use rand::{RngCore, FromEntropy, SeedableRng};
-use criterion::{Criterion, Fun};
+use criterion::{Bencher, Benchmark, Criterion, Fun, ParameterizedBenchmark, Throughput};
 
 fn fill(c: &mut Criterion) {
     const BUF_SIZE: usize = 1024 * 1024 * 100;
@@ -17,7 +17,7 @@ fn fill(c: &mut Criterion) {
         ]);
         let mut buf = vec![0; BUF_SIZE];
 
-        Fun::new("aes", move |b, _| b.iter(|| rng.fill_bytes(&mut buf)))
+        move |b: &mut Bencher| b.iter(|| rng.fill_bytes(&mut buf))
     };
     let fill_aescore = {
         let mut rng = aesrng::AesCore::from_seed([
@@ -26,22 +26,37 @@ fn fill(c: &mut Criterion) {
         ]);
         let mut buf = vec![0; BUF_SIZE];
 
-        Fun::new("aescore", move |b, _| b.iter(|| rng.fill(&mut buf)))
+        move |b: &mut Bencher| b.iter(|| rng.fill(&mut buf))
+    };
+    let fill_aes256 = {
+        let mut rng = aesrng::AesRng256::from_seed(aesrng::Seed384([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+        ]));
+        let mut buf = vec![0; BUF_SIZE];
+
+        move |b: &mut Bencher| b.iter(|| rng.fill_bytes(&mut buf))
     };
     let fill_xoshiro =
         {
             let mut rng = rand_xoshiro::Xoshiro128StarStar::seed_from_u64(1);
             let mut buf = vec![0; BUF_SIZE];
 
-            Fun::new("xoshiro", move |b, _| b.iter(|| rng.fill_bytes(&mut buf)))
+            move |b: &mut Bencher| b.iter(|| rng.fill_bytes(&mut buf))
         };
     let fill_std = {
         let mut rng = rand::rngs::StdRng::from_entropy();
         let mut buf = vec![0; BUF_SIZE];
 
-        Fun::new("std", move |b, _| b.iter(|| rng.fill_bytes(&mut buf)))
+        move |b: &mut Bencher| b.iter(|| rng.fill_bytes(&mut buf))
     };
-    c.bench_functions("fill", vec![fill_aes, fill_aescore, fill_xoshiro, fill_std], ());
+    let benchmark = Benchmark::new("aes", fill_aes)
+        .with_function("aescore", fill_aescore)
+        .with_function("aes256", fill_aes256)
+        .with_function("xoshiro", fill_xoshiro)
+        .with_function("std", fill_std)
+        .throughput(Throughput::Bytes(BUF_SIZE as u32));
+    c.bench("fill", benchmark);
 }
 
 fn next_u64(c: &mut Criterion) {
@@ -50,18 +65,72 @@ fn next_u64(c: &mut Criterion) {
             0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
             11, 12, 13, 14, 15,
         ]);
-        Fun::new("aes", move |b, _| b.iter(|| rng.next_u64()))
+        move |b: &mut Bencher| b.iter(|| rng.next_u64())
     };
     let next_xoshiro =
         {
             let mut rng = rand_xoshiro::Xoshiro128StarStar::seed_from_u64(1);
-            Fun::new("xoshiro", move |b, _| b.iter(|| rng.next_u64()))
+            move |b: &mut Bencher| b.iter(|| rng.next_u64())
         };
     let next_std = {
         let mut rng = rand::rngs::StdRng::from_entropy();
-        Fun::new("std", move |b, _| b.iter(|| rng.next_u64()))
+        move |b: &mut Bencher| b.iter(|| rng.next_u64())
+    };
+    let benchmark = Benchmark::new("aes", next_aes)
+        .with_function("xoshiro", next_xoshiro)
+        .with_function("std", next_std)
+        .throughput(Throughput::Bytes(8));
+    c.bench("next_u64", benchmark);
+}
+
+fn next_u32(c: &mut Criterion) {
+    let next_aes = {
+        let mut rng = aesrng::AesRng::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+        move |b: &mut Bencher| b.iter(|| rng.next_u32())
+    };
+    let next_aes64 = {
+        let mut rng = aesrng::AesRng64::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+        move |b: &mut Bencher| b.iter(|| rng.next_u32())
     };
-    c.bench_functions("next_u64", vec![next_aes, next_xoshiro, next_std], ());
+    let benchmark = Benchmark::new("aes", next_aes)
+        .with_function("aes64", next_aes64)
+        .throughput(Throughput::Bytes(4));
+    c.bench("next_u32", benchmark);
+}
+
+fn next_loop(c: &mut Criterion) {
+    let new_rng = || aesrng::AesRng::from_seed([
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+        11, 12, 13, 14, 15,
+    ]);
+    let benchmark = ParameterizedBenchmark::new("next_u32", move |b, &n| {
+            let mut rng = new_rng();
+            b.iter(|| {
+                let mut x = 0u32;
+                for _ in 0..n {
+                    x = x.wrapping_add(rng.next_u32());
+                }
+                x
+            })
+        }, vec![1, 8, 64, 512])
+        .with_function("next_u64", move |b, &n| {
+            let mut rng = new_rng();
+            b.iter(|| {
+                let mut x = 0u64;
+                for _ in 0..n {
+                    x = x.wrapping_add(rng.next_u64());
+                }
+                x
+            })
+        })
+        .throughput(|&n| Throughput::Elements(n));
+    c.bench("next_loop", benchmark);
 }
 
 fn new(c: &mut Criterion) {
@@ -81,5 +150,45 @@ fn new(c: &mut Criterion) {
     c.bench_functions("new", vec![new_aes, new_aescore, new_xoshiro, new_std], ());
 }
 
-criterion_group!(benches, fill, next_u64, new);
+fn fill_unroll(c: &mut Criterion) {
+    const SIZES: [usize; 9] = [16, 32, 64, 128, 256, 512, 1024, 4096, 1024 * 1024];
+    fn bench<const UNROLL: usize>(b: &mut criterion::Bencher, &size: &usize) {
+        let mut rng = aesrng::AesCore::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+        let mut buf = vec![0; size];
+        b.iter(|| rng.fill_unrolled::<UNROLL>(&mut buf))
+    }
+    let benchmark = ParameterizedBenchmark::new("1", bench::<1>, SIZES.to_vec())
+        .with_function("2", bench::<2>)
+        .with_function("4", bench::<4>)
+        .with_function("8", bench::<8>)
+        .with_function("16", bench::<16>)
+        .throughput(|&size| Throughput::Bytes(size as u32));
+    c.bench("fill_unroll", benchmark);
+}
+
+fn turbo_fill(c: &mut Criterion) {
+    let new_rng = || aesrng::AesCore::from_seed([
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+        11, 12, 13, 14, 15,
+    ]);
+    let fill = {
+        let mut rng = new_rng();
+        let mut buf = [0; 128];
+        move |b: &mut Bencher| b.iter(|| rng.fill(&mut buf))
+    };
+    let turbo = {
+        let mut rng = new_rng();
+        let mut buf = [0; 128];
+        move |b: &mut Bencher| b.iter(|| rng.turbo_fill_128(&mut buf))
+    };
+    let benchmark = Benchmark::new("fill", fill)
+        .with_function("turbo_fill_128", turbo)
+        .throughput(Throughput::Bytes(128));
+    c.bench("turbo_fill", benchmark);
+}
+
+criterion_group!(benches, fill, next_u64, next_u32, next_loop, new, fill_unroll, turbo_fill);
 criterion_main!(benches);
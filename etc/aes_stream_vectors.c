@@ -0,0 +1,117 @@
+/*
+ * Prints 256-byte output vectors of the aes-stream algorithm for the seeds
+ * used by the `tests::compat` module of this crate.
+ *
+ * This follows the structure of aes-stream.c from
+ * https://github.com/jedisct1/aes-stream (AES-NI code path), independently of
+ * the Rust port.
+ *
+ *     cc -O2 -maes -msse2 -o aes_stream_vectors etc/aes_stream_vectors.c
+ *     ./aes_stream_vectors
+ */
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <wmmintrin.h>
+
+typedef struct {
+    __m128i round_keys[11];
+    __m128i counter;
+} aes_stream_state;
+
+#define DRC(ROUND, RC)                                       \
+    do {                                                     \
+        s = _mm_aeskeygenassist_si128(t1, (RC));             \
+        round_keys[ROUND] = t1;                              \
+        t1 = _mm_xor_si128(t1, _mm_slli_si128(t1, 4));       \
+        t1 = _mm_xor_si128(t1, _mm_slli_si128(t1, 8));       \
+        t1 = _mm_xor_si128(t1, _mm_shuffle_epi32(s, 0xff));  \
+    } while (0)
+
+static void
+aes_key_expand_128(__m128i round_keys[11], __m128i t1)
+{
+    __m128i s;
+
+    DRC(0, 1); DRC(1, 2); DRC(2, 4); DRC(3, 8); DRC(4, 16);
+    DRC(5, 32); DRC(6, 64); DRC(7, 128); DRC(8, 27); DRC(9, 54);
+    round_keys[10] = t1;
+}
+
+#define COMPUTE_ROUNDS(N)                                                       \
+    do {                                                                        \
+        r##N = _mm_aesenc_si128(_mm_xor_si128(c##N, rkeys[0]), rkeys[1]);      \
+        r##N = _mm_aesenc_si128(_mm_aesenc_si128(r##N, rkeys[2]), rkeys[3]);   \
+        r##N = _mm_aesenc_si128(_mm_aesenc_si128(r##N, rkeys[4]), rkeys[5]);   \
+        s##N = r##N;                                                            \
+        r##N = _mm_aesenc_si128(_mm_aesenc_si128(r##N, rkeys[6]), rkeys[7]);   \
+        r##N = _mm_aesenc_si128(_mm_aesenc_si128(r##N, rkeys[8]), rkeys[9]);   \
+        r##N = _mm_xor_si128(s##N, _mm_aesenclast_si128(r##N, rkeys[10]));     \
+    } while (0)
+
+static void
+aes_stream_init(aes_stream_state *st, const unsigned char seed[32])
+{
+    aes_key_expand_128(st->round_keys, _mm_loadu_si128((const __m128i *) seed));
+    st->counter = _mm_loadu_si128((const __m128i *) (seed + 16));
+}
+
+static void
+aes_stream(aes_stream_state *st, unsigned char *buf, size_t buf_len)
+{
+    const __m128i *rkeys = st->round_keys;
+    const __m128i  one = _mm_set_epi64x(0, 1);
+    __m128i        c0 = st->counter, r0, s0;
+    unsigned char  t[16];
+
+    while (buf_len >= 16) {
+        COMPUTE_ROUNDS(0);
+        c0 = _mm_add_epi64(c0, one);
+        _mm_storeu_si128((__m128i *) buf, r0);
+        buf += 16;
+        buf_len -= 16;
+    }
+    if (buf_len > 0) {
+        COMPUTE_ROUNDS(0);
+        c0 = _mm_add_epi64(c0, one);
+        _mm_storeu_si128((__m128i *) t, r0);
+        memcpy(buf, t, buf_len);
+    }
+    st->counter = c0;
+    c0 = _mm_xor_si128(c0, _mm_set_epi64x(1ULL << 63, 0));
+    COMPUTE_ROUNDS(0);
+    aes_key_expand_128(st->round_keys, r0);
+}
+
+static void
+print_vector(const unsigned char seed[32])
+{
+    aes_stream_state st;
+    unsigned char    buf[256];
+    size_t           i;
+
+    aes_stream_init(&st, seed);
+    aes_stream(&st, buf, 128);
+    aes_stream(&st, buf + 128, 128);
+    for (i = 0; i < sizeof buf; i++) {
+        printf("%02x", buf[i]);
+    }
+    printf("\n");
+}
+
+int
+main(void)
+{
+    unsigned char seed[32];
+    size_t        i;
+
+    memset(seed, 0, sizeof seed);
+    print_vector(seed);
+    memset(seed, 0xff, sizeof seed);
+    print_vector(seed);
+    for (i = 0; i < sizeof seed; i++) {
+        seed[i] = (unsigned char) (i * 37 + 11);
+    }
+    print_vector(seed);
+    return 0;
+}
@@ -0,0 +1,29 @@
+//! Fill buffers of arbitrary length, to catch off-by-one errors in the loops
+//! of `AesCore::fill`.
+//!
+//! Run with `cargo fuzz run fill`.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate aesrng;
+
+use aesrng::AesCore;
+use aesrng::rand_core::SeedableRng;
+
+/// Upper bound for the buffer length, to keep the allocations small.
+const MAX_LEN: usize = 1 << 16;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 36 {
+        return;
+    }
+    let mut seed = [0; 32];
+    seed.copy_from_slice(&data[..32]);
+    let mut len = [0; 4];
+    len.copy_from_slice(&data[32..36]);
+    let len = u32::from_le_bytes(len) as usize % (MAX_LEN + 1);
+
+    let mut rng = AesCore::from_seed(seed);
+    let mut buf = vec![0; len];
+    rng.fill(&mut buf);
+});
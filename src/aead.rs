@@ -0,0 +1,278 @@
+//! AES256-GCM authenticated encryption, enabled by the `aead` feature.
+//!
+//! This combines AES256 in counter mode with GHASH for authentication, as
+//! specified in NIST SP 800-38D. GHASH uses the PCLMULQDQ instruction if the
+//! `pclmulqdq` target feature is enabled at compile time, and a portable
+//! constant-time implementation otherwise.
+//!
+//! ```
+//! use aesrng::aead::{open, seal};
+//!
+//! let key = [1; 32];
+//! let nonce = [2; 12];
+//! let sealed = seal(&key, &nonce, b"attack at dawn", b"header");
+//! assert_eq!(open(&key, &nonce, &sealed, b"header").unwrap(), b"attack at dawn");
+//! assert!(open(&key, &nonce, &sealed, b"other header").is_err());
+//! ```
+
+#[cfg(all(target_arch = "x86", target_feature = "pclmulqdq"))]
+use core::arch::x86::*;
+#[cfg(all(target_arch = "x86_64", target_feature = "pclmulqdq"))]
+use core::arch::x86_64::*;
+use core::fmt;
+
+use alloc::vec::Vec;
+use rand_core::SeedableRng;
+
+use {AesCore256, Block, Seed384};
+
+/// Size of the authentication tag appended by `seal`, in bytes.
+pub const TAG_SIZE: usize = 16;
+
+/// Maximum length of the plaintext in bytes, see NIST SP 800-38D.
+const MAX_PLAINTEXT_LEN: u64 = (1 << 36) - 32;
+
+/// Encrypt and authenticate `plaintext`, and authenticate `aad`.
+///
+/// Returns the ciphertext followed by the 16-byte authentication tag. A nonce
+/// must never be reused with the same key, otherwise the keystream repeats and
+/// the authentication key can be recovered.
+///
+/// Panics if the AES instructions are not available or if the plaintext is
+/// longer than 2^36 - 32 bytes.
+#[must_use]
+pub fn seal(key: &[u8; 32], nonce: &[u8; 12], plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    assert!(plaintext.len() as u64 <= MAX_PLAINTEXT_LEN, "plaintext is too long");
+    let gcm = Gcm::new(key, nonce);
+    let mut sealed = Vec::with_capacity(plaintext.len() + TAG_SIZE);
+    sealed.extend_from_slice(plaintext);
+    gcm.apply_keystream(&mut sealed);
+    let tag = gcm.tag(aad, &sealed);
+    sealed.extend_from_slice(&tag);
+    sealed
+}
+
+/// Verify and decrypt the output of `seal`.
+///
+/// Returns an error if the ciphertext, the tag or `aad` were modified, or if
+/// the key or nonce are different from the ones used for sealing. Nothing is
+/// decrypted in this case.
+///
+/// Panics if the AES instructions are not available.
+pub fn open(key: &[u8; 32], nonce: &[u8; 12], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AuthError> {
+    if sealed.len() < TAG_SIZE || (sealed.len() - TAG_SIZE) as u64 > MAX_PLAINTEXT_LEN {
+        return Err(AuthError);
+    }
+    let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_SIZE);
+    let gcm = Gcm::new(key, nonce);
+    // Compare the tags in constant time.
+    let diff = gcm.tag(aad, ciphertext).iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(AuthError);
+    }
+    let mut plaintext = ciphertext.to_vec();
+    gcm.apply_keystream(&mut plaintext);
+    Ok(plaintext)
+}
+
+/// Error returned by `open` if the authentication failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthError;
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "authentication failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for AuthError {}
+
+/// The state of AES256-GCM for a given key and nonce.
+struct Gcm {
+    cipher: AesCore256,
+    /// The hash key, the encryption of the zero block.
+    h: u128,
+    /// The pre-counter block.
+    j0: u128,
+}
+
+impl Gcm {
+    fn new(key: &[u8; 32], nonce: &[u8; 12]) -> Gcm {
+        let mut seed = Seed384::default();
+        seed.0[..32].copy_from_slice(key);
+        let cipher = AesCore256::from_seed(seed);
+        let mut j0 = [0; 16];
+        j0[..12].copy_from_slice(nonce);
+        j0[15] = 1;
+        // `AesCore256::from_seed` panics if the AES instructions are not
+        // available.
+        let h = unsafe { encrypt(&cipher, 0) };
+        Gcm {
+            cipher,
+            h,
+            j0: u128::from_be_bytes(j0),
+        }
+    }
+
+    /// XOR the keystream into `data`, starting with the counter block after
+    /// `j0`.
+    fn apply_keystream(&self, data: &mut [u8]) {
+        unsafe { self.apply_keystream_impl(data) }
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn apply_keystream_impl(&self, data: &mut [u8]) {
+        let mut counter = self.j0;
+        for chunk in data.chunks_mut(16) {
+            counter = inc32(counter);
+            let keystream = encrypt(&self.cipher, counter).to_be_bytes();
+            for (x, k) in chunk.iter_mut().zip(&keystream) {
+                *x ^= k;
+            }
+        }
+    }
+
+    /// Compute the authentication tag.
+    fn tag(&self, aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_SIZE] {
+        let s = ghash(self.h, aad, ciphertext);
+        let mask = unsafe { encrypt(&self.cipher, self.j0) };
+        (s ^ mask).to_be_bytes()
+    }
+}
+
+/// Encrypt a block given as a big-endian integer.
+#[inline]
+#[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+unsafe fn encrypt(cipher: &AesCore256, block: u128) -> u128 {
+    let r = cipher.encrypt_block(Block(block.to_be_bytes()).to_m128i());
+    u128::from_be_bytes(r.bytes())
+}
+
+/// Increment the lower 32 bits of the counter block, modulo 2^32.
+fn inc32(counter: u128) -> u128 {
+    let low = (counter as u32).wrapping_add(1);
+    (counter & !0xffff_ffff) | u128::from(low)
+}
+
+/// Hash the zero-padded AAD and ciphertext, followed by their lengths in
+/// bits.
+fn ghash(h: u128, aad: &[u8], ciphertext: &[u8]) -> u128 {
+    let mut y = 0;
+    for data in &[aad, ciphertext] {
+        for chunk in data.chunks(16) {
+            let mut block = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            y = gf_mul(y ^ u128::from_be_bytes(block), h);
+        }
+    }
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    gf_mul(y ^ lengths, h)
+}
+
+/// Multiply in GF(2^128), using the bit order of GCM.
+///
+/// This is the shift-and-add algorithm of NIST SP 800-38D, using masks instead
+/// of branches so it runs in constant time.
+#[cfg(not(all(any(target_arch = "x86_64", target_arch = "x86"), target_feature = "pclmulqdq")))]
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        z ^= v & ((x >> i) & 1).wrapping_neg();
+        v = (v >> 1) ^ (R & (v & 1).wrapping_neg());
+    }
+    z
+}
+
+/// Multiply in GF(2^128), using the bit order of GCM.
+///
+/// This is algorithm 5 of Intel's "Carry-Less Multiplication Instruction and
+/// its Usage for Computing the GCM Mode" white paper. Interpreting the blocks
+/// as big-endian integers takes care of the byte reflection.
+#[cfg(all(any(target_arch = "x86_64", target_arch = "x86"), target_feature = "pclmulqdq"))]
+fn gf_mul(x: u128, y: u128) -> u128 {
+    unsafe {
+        let a = _mm_set_epi64x((x >> 64) as i64, x as i64);
+        let b = _mm_set_epi64x((y >> 64) as i64, y as i64);
+
+        // Compute the 256-bit carry-less product.
+        let mut lo = _mm_clmulepi64_si128(a, b, 0x00);
+        let mut mid = _mm_xor_si128(_mm_clmulepi64_si128(a, b, 0x10), _mm_clmulepi64_si128(a, b, 0x01));
+        let mut hi = _mm_clmulepi64_si128(a, b, 0x11);
+        lo = _mm_xor_si128(lo, _mm_slli_si128(mid, 8));
+        hi = _mm_xor_si128(hi, _mm_srli_si128(mid, 8));
+
+        // Shift the product left by one bit, because the operands are bit
+        // reflected.
+        let lo_carry = _mm_srli_epi32(lo, 31);
+        mid = _mm_srli_epi32(hi, 31);
+        lo = _mm_slli_epi32(lo, 1);
+        hi = _mm_slli_epi32(hi, 1);
+        hi = _mm_or_si128(hi, _mm_srli_si128(lo_carry, 12));
+        hi = _mm_or_si128(hi, _mm_slli_si128(mid, 4));
+        lo = _mm_or_si128(lo, _mm_slli_si128(lo_carry, 4));
+
+        // Reduce modulo x^128 + x^7 + x^2 + x + 1.
+        let mut t = _mm_xor_si128(_mm_slli_epi32(lo, 31), _mm_slli_epi32(lo, 30));
+        t = _mm_xor_si128(t, _mm_slli_epi32(lo, 25));
+        let carry = _mm_srli_si128(t, 4);
+        lo = _mm_xor_si128(lo, _mm_slli_si128(t, 12));
+        let mut u = _mm_xor_si128(_mm_srli_epi32(lo, 1), _mm_srli_epi32(lo, 2));
+        u = _mm_xor_si128(u, _mm_srli_epi32(lo, 7));
+        u = _mm_xor_si128(u, carry);
+        lo = _mm_xor_si128(lo, u);
+        let r = _mm_xor_si128(hi, lo);
+
+        let mut bytes = [0u8; 16];
+        _mm_storeu_si128(bytes.as_mut_ptr() as *mut __m128i, r);
+        u128::from_le_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, seal, AuthError};
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn gcm_spec() {
+        // Test cases 13, 14 and 16 from "The Galois/Counter Mode of Operation
+        // (GCM)" by McGrew and Viega.
+        let sealed = seal(&[0; 32], &[0; 12], &[], &[]);
+        assert_eq!(sealed, hex("530f8afbc74536b9a963b4f1c4cb738b"));
+
+        let sealed = seal(&[0; 32], &[0; 12], &[0; 16], &[]);
+        assert_eq!(sealed, hex("cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919"));
+
+        let mut key = [0; 32];
+        key.copy_from_slice(&hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308"));
+        let mut nonce = [0; 12];
+        nonce.copy_from_slice(&hex("cafebabefacedbaddecaf888"));
+        let plaintext = hex("d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39");
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let sealed = seal(&key, &nonce, &plaintext, &aad);
+        assert_eq!(sealed, hex("522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f66276fc6ece0f4e1768cddf8853bb2d551b"));
+        assert_eq!(open(&key, &nonce, &sealed, &aad), Ok(plaintext));
+    }
+
+    #[test]
+    fn tampering() {
+        let key = [7; 32];
+        let nonce = [3; 12];
+        let sealed = seal(&key, &nonce, b"attack at dawn", b"header");
+        for i in 0..sealed.len() {
+            let mut modified = sealed.clone();
+            modified[i] ^= 1;
+            assert_eq!(open(&key, &nonce, &modified, b"header"), Err(AuthError));
+        }
+        assert_eq!(open(&key, &nonce, &sealed[1..], b"header"), Err(AuthError));
+        assert_eq!(open(&key, &nonce, &sealed[..15], b"header"), Err(AuthError));
+        assert_eq!(open(&key, &[4; 12], &sealed, b"header"), Err(AuthError));
+        assert_eq!(open(&[8; 32], &nonce, &sealed, b"header"), Err(AuthError));
+    }
+}
@@ -0,0 +1,360 @@
+//! Fast-key-erasure random-number generator based on AES256.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use rand_core::{CryptoRng, RngCore, SeedableRng, Error};
+use rand_core::block::{BlockRngCore, BlockRng};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use byte_slice::AsByteSliceMut;
+use {Block, M128, AESRNG_BUFSIZE, Results, assert_aligned, is_aes_available};
+#[cfg(feature = "aead")]
+use encrypt_block;
+#[cfg(feature = "zeroize")]
+use erase;
+
+const ROUNDS: usize = 14;
+const SEEDBYTES: usize = 48;
+
+type RoundKeys = [M128; ROUNDS + 1];
+
+// Derive the next even round key from the previous even round key `$t1`,
+// using the key generation assist computed on the previous odd round key.
+macro_rules! drc_even {
+    ($round:expr, $rc:expr, $s:ident, $t1:ident, $t3:ident, $round_keys:expr) => (
+        $s = keygenassist!($t3, $rc);
+        $t1 = $t1 ^ shiftl!($t1, 4);
+        $t1 = $t1 ^ shiftl!($t1, 8);
+        $t1 = $t1 ^ shuffle!($s, 0xff);
+        $round_keys[$round] = $t1;
+    );
+}
+
+// Derive the next odd round key from the previous odd round key `$t3`,
+// using the key generation assist computed on the current even round key.
+macro_rules! drc_odd {
+    ($round:expr, $s:ident, $t1:ident, $t3:ident, $round_keys:expr) => (
+        $s = keygenassist!($t1, 0);
+        $t3 = $t3 ^ shiftl!($t3, 4);
+        $t3 = $t3 ^ shiftl!($t3, 8);
+        $t3 = $t3 ^ shuffle!($s, 0xaa);
+        $round_keys[$round] = $t3;
+    );
+}
+
+#[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+unsafe fn aes_key_expand_256(round_keys: &mut RoundKeys, mut t1: M128, mut t3: M128) {
+    let mut s: M128;
+    round_keys[0] = t1;
+    round_keys[1] = t3;
+    drc_even!(2, 1, s, t1, t3, round_keys);
+    drc_odd!(3, s, t1, t3, round_keys);
+    drc_even!(4, 2, s, t1, t3, round_keys);
+    drc_odd!(5, s, t1, t3, round_keys);
+    drc_even!(6, 4, s, t1, t3, round_keys);
+    drc_odd!(7, s, t1, t3, round_keys);
+    drc_even!(8, 8, s, t1, t3, round_keys);
+    drc_odd!(9, s, t1, t3, round_keys);
+    drc_even!(10, 16, s, t1, t3, round_keys);
+    drc_odd!(11, s, t1, t3, round_keys);
+    drc_even!(12, 32, s, t1, t3, round_keys);
+    drc_odd!(13, s, t1, t3, round_keys);
+    drc_even!(14, 64, s, t1, t3, round_keys);
+}
+
+macro_rules! compute_rounds_256 {
+    ($n:expr, $c:ident, $r:ident, $s:ident, $round_keys:expr) => (
+        $r[$n] = ($c[$n] ^ $round_keys[0]).encrypt($round_keys[1]);
+        $r[$n] = $r[$n].encrypt($round_keys[2]).encrypt($round_keys[3]);
+        $r[$n] = $r[$n].encrypt($round_keys[4]).encrypt($round_keys[5]);
+        $r[$n] = $r[$n].encrypt($round_keys[6]).encrypt($round_keys[7]);
+        $s[$n] = $r[$n];
+        $r[$n] = $r[$n].encrypt($round_keys[8]).encrypt($round_keys[9]);
+        $r[$n] = $r[$n].encrypt($round_keys[10]).encrypt($round_keys[11]);
+        $r[$n] = $r[$n].encrypt($round_keys[12]).encrypt($round_keys[13]);
+        $r[$n] = $s[$n] ^ $r[$n].encrypt_last($round_keys[14]);
+    );
+}
+
+/// Seed for `AesCore256`: a 32 byte key followed by a 16 byte counter.
+///
+/// Like the 32 byte seed of `AesCore`, the seed is exactly the initial state,
+/// so it is 48 bytes rather than 64: there is nothing to use further bytes
+/// for without hashing them into the key. This is a newtype because arrays
+/// longer than 32 do not implement `Default`, as required for
+/// `SeedableRng::Seed`.
+#[derive(Clone)]
+pub struct Seed384(pub [u8; SEEDBYTES]);
+
+impl Default for Seed384 {
+    fn default() -> Seed384 {
+        Seed384([0; SEEDBYTES])
+    }
+}
+
+impl AsMut<[u8]> for Seed384 {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for Seed384 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Seed384 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0[..].fmt(f)
+    }
+}
+
+/// A fast-key-erasure random-number generator using AES-NI with AES256.
+///
+/// This works like `AesCore`, but uses a 256 bit key and 14 rounds, at the
+/// cost of a somewhat lower throughput.
+#[repr(align(16))]
+#[derive(Clone)]
+pub struct AesCore256 {
+    round_keys: RoundKeys,
+    counter: M128,
+}
+
+impl AesCore256 {
+    /// Fill the given buffer with random data.
+    ///
+    /// Erases the key after filling the buffer.
+    #[inline]
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        // An `AesCore256` can only be created if the AES instructions are
+        // available.
+        unsafe { self.fill_impl(buffer) }
+    }
+
+    /// Fill the given 16-byte aligned buffer with random data.
+    ///
+    /// See `AesCore::fill_aligned`.
+    #[inline]
+    pub fn fill_aligned(&mut self, buffer: &mut [u8]) {
+        assert_aligned(buffer);
+        unsafe { self.fill_aligned_impl(buffer) }
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_impl(&mut self, buffer: &mut [u8]) {
+        fill!(compute_rounds_256, store, self.round_keys, self.counter, buffer);
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_aligned_impl(&mut self, buffer: &mut [u8]) {
+        fill!(compute_rounds_256, store_aligned, self.round_keys, self.counter, buffer);
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn erase_key(&mut self) {
+        let zero = M128::from((0, 0));
+        let canary = M128::from((1 << 63, 0));
+        let c = [self.counter ^ canary, (self.counter + M128::from((0, 1))) ^ canary];
+        let mut r = [zero; 2];
+        let mut s = [zero; 2];
+        compute_rounds_256!(0, c, r, s, self.round_keys);
+        compute_rounds_256!(1, c, r, s, self.round_keys);
+        aes_key_expand_256(&mut self.round_keys, r[0], r[1]);
+    }
+
+    /// Encrypt a single block using the plain AES256 block cipher.
+    ///
+    /// This does not erase the key.
+    #[cfg(feature = "aead")]
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    pub(crate) unsafe fn encrypt_block(&self, block: M128) -> M128 {
+        encrypt_block(&self.round_keys, block)
+    }
+}
+
+impl SeedableRng for AesCore256 {
+    type Seed = Seed384;
+
+    /// Create a new `AesCore256` using the given seed.
+    ///
+    /// Panics if the AES instructions are not available.
+    fn from_seed(seed: Seed384) -> AesCore256 {
+        assert!(is_aes_available(), "AES instructions are not available");
+        let zero = M128::from((0, 0));
+        let mut round_keys: RoundKeys = [zero; ROUNDS + 1];
+        let key_lo = Block::from_slice(&seed.0[..16]).to_m128i();
+        let key_hi = Block::from_slice(&seed.0[16..32]).to_m128i();
+        let counter = Block::from_slice(&seed.0[32..]).to_m128i();
+
+        unsafe { aes_key_expand_256(&mut round_keys, key_lo, key_hi) };
+
+        AesCore256 {
+            round_keys,
+            counter,
+        }
+    }
+}
+
+// Custom Debug implementation that does not expose the internal state
+impl fmt::Debug for AesCore256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AesCore256 {{}}")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for AesCore256 {
+    fn zeroize(&mut self) {
+        erase(&mut self.round_keys);
+        erase(::core::slice::from_mut(&mut self.counter));
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for AesCore256 {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for AesCore256 {}
+
+impl BlockRngCore for AesCore256 {
+    type Item = u32;
+    type Results = Results<AESRNG_BUFSIZE>;
+
+    #[inline(always)]
+    fn generate(&mut self, results: &mut Self::Results) {
+        self.fill(results.0.as_byte_slice_mut());
+        results.0.to_le();
+    }
+}
+
+/// A fast-key-erasure random-number generator using AES-NI with AES256.
+///
+/// See `AesRng` for details, this only differs in the underlying block
+/// cipher.
+#[derive(Clone, Debug)]
+pub struct AesRng256(BlockRng<AesCore256>);
+
+impl RngCore for AesRng256 {
+    #[inline(always)]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.core.fill(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.core.fill(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for AesRng256 {
+    type Seed = <AesCore256 as SeedableRng>::Seed;
+
+    #[inline]
+    fn from_seed(seed: Self::Seed) -> Self {
+        AesRng256(BlockRng::<AesCore256>::from_seed(seed))
+    }
+
+    fn from_rng<R: RngCore>(rng: R) -> Result<Self, Error> {
+        BlockRng::<AesCore256>::from_rng(rng).map(AesRng256)
+    }
+}
+
+impl CryptoRng for AesRng256 {}
+
+/// Reading never fails and always fills the whole buffer.
+#[cfg(feature = "std")]
+impl io::Read for AesRng256 {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill_bytes(buf);
+        Ok(buf.len())
+    }
+}
+
+/// Erases the key and overwrites the buffered values.
+#[cfg(feature = "zeroize")]
+impl Zeroize for AesRng256 {
+    fn zeroize(&mut self) {
+        self.0.core.zeroize();
+        // The buffer is not accessible, replacing it drops and zeroizes it.
+        self.0 = BlockRng::new(self.0.core.clone());
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for AesRng256 {}
+
+#[cfg(test)]
+mod tests {
+    extern crate itertools;
+
+    use self::itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn size() {
+        assert_eq!(::core::mem::size_of::<AesCore256>(), (ROUNDS + 1) * 16 + 16);
+    }
+
+    #[test]
+    fn from_seed() {
+        // Key expansion example from FIPS 197, appendix A.3.
+        let mut seed = Seed384::default();
+        seed.0[..32].copy_from_slice(&[
+            0x60, 0x3d, 0xeb, 0x10, 0x15, 0xca, 0x71, 0xbe, 0x2b, 0x73, 0xae, 0xf0, 0x85, 0x7d,
+            0x77, 0x81, 0x1f, 0x35, 0x2c, 0x07, 0x3b, 0x61, 0x08, 0xd7, 0x2d, 0x98, 0x10, 0xa3,
+            0x09, 0x14, 0xdf, 0xf4,
+        ]);
+        let rng = AesCore256::from_seed(seed);
+        let mut hex = String::new();
+        for &key in rng.round_keys.iter() {
+            let buf = key.bytes();
+            hex.push_str(&format!("{:02x}", buf.iter().format("")));
+        }
+        let expected = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff49ba354118e6925afa51a8b5f2067fcdea8b09c1a93d194cdbe49846eb75d5b9ad59aecb85bf3c917fee94248de8ebe96b5a9328a2678a647983122292f6c79b3812c81addadf48ba24360af2fab8b46498c5bfc9bebd198e268c3ba709e0421468007bacb2df331696e939e46c518d80c814e20476a9fb8a5025c02d59c58239de1369676ccc5a71fa2563959674ee155886ca5d2e2f31d77e0af1fa27cf73c3749c47ab18501ddae2757e4f7401905acafaaae3e4d59b349adf6acebd10190dfe4890d1e6188d0b046df344706c631e";
+        assert_eq!(hex, expected);
+    }
+
+    #[test]
+    fn fill() {
+        let mut seed = Seed384::default();
+        for (i, x) in seed.0.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        let mut rng = AesCore256::from_seed(seed);
+
+        let mut buf = vec![0; 200];
+        rng.fill(&mut buf);
+        let hex = format!("{:02x}", buf.iter().format(""));
+        let expected = "6ebf6608fe2f51c8e7229f84835a852e7a227c353a6efa5a82f95bba280c5f0e5a7dc6cdf80cc87280378661597f199c084497d3b6b743de1074a3aa3d204dd48a7d4af74ee0dae616bb1165545292e15b38cfcd626d8b07ba2f8c817b1c706576df5ff1a82c2d0d8c9049ac4b2b5bcdf92add073dda907593d7904facd0934d95e0eca5c539f115e51a001ef8978d4330d58b7b65b5843537abb31aadb14645828ade2b4271a8ab41ad21840f1087f11a61f7caf643a57ee88b28f3c6e837bd2147f35a6ffd52ba";
+        assert_eq!(hex, expected);
+
+        // The key was erased, so this uses the next key.
+        let mut buf = vec![0; 40];
+        rng.fill(&mut buf);
+        let hex = format!("{:02x}", buf.iter().format(""));
+        let expected = "4ec164147a0b171930a4844e8902a24e11a216e1c3ff60a560950525bb2e56ce9d043c2bad779556";
+        assert_eq!(hex, expected);
+    }
+}
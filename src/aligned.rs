@@ -0,0 +1,73 @@
+//! Heap buffers aligned to cache lines.
+
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+use core::slice;
+
+/// Alignment of `AlignedBytes`, the size of a cache line on most CPUs.
+pub const CACHE_LINE: usize = 64;
+
+/// A heap-allocated byte buffer aligned to 64 bytes, see
+/// `AesCore::fill_bytes_aligned`.
+///
+/// This dereferences to `[u8]`. It cannot be converted to a `Box<[u8]>`,
+/// because a `Box` would free the memory with the wrong alignment.
+pub struct AlignedBytes {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// `AlignedBytes` owns its allocation like a `Box<[u8]>`.
+unsafe impl Send for AlignedBytes {}
+unsafe impl Sync for AlignedBytes {}
+
+impl AlignedBytes {
+    /// Allocate `len` zeroed bytes aligned to 64 bytes.
+    ///
+    /// Panics if `len` overflows `isize` when rounded up to the alignment.
+    pub(crate) fn zeroed(len: usize) -> AlignedBytes {
+        if len == 0 {
+            // A dangling pointer is valid for empty slices, but it still has
+            // to be aligned.
+            let ptr = NonNull::new(CACHE_LINE as *mut u8).unwrap();
+            return AlignedBytes { ptr, len };
+        }
+        let layout = Layout::from_size_align(len, CACHE_LINE).expect("buffer is too large");
+        let ptr = unsafe { alloc_zeroed(layout) };
+        match NonNull::new(ptr) {
+            Some(ptr) => AlignedBytes { ptr, len },
+            None => handle_alloc_error(layout),
+        }
+    }
+}
+
+impl Deref for AlignedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for AlignedBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for AlignedBytes {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            let layout = Layout::from_size_align(self.len, CACHE_LINE).unwrap();
+            unsafe { dealloc(self.ptr.as_ptr(), layout) };
+        }
+    }
+}
+
+impl fmt::Debug for AlignedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("AlignedBytes").field(&&**self).finish()
+    }
+}
@@ -0,0 +1,108 @@
+//! An RNG using AES if available and ChaCha20 otherwise.
+
+use rand_chacha::ChaChaRng;
+use rand_core::{CryptoRng, RngCore, SeedableRng, Error};
+
+use {AesRng, SEEDBYTES, is_aes_available};
+
+/// Either an `AesRng` or a ChaCha20 RNG, depending on the CPU.
+///
+/// Use `AnyRng::from_seed` or `AnyRng::new_best` to pick `AesRng` if the AES
+/// instructions are available and ChaCha20 as a software fallback otherwise.
+/// The output depends on the variant, so the same seed gives different
+/// results on different CPUs.
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]  // Boxing would require `alloc`.
+pub enum AnyRng {
+    /// The AES-based RNG of this crate.
+    Aes(AesRng),
+    /// ChaCha with 20 rounds, as implemented by `rand_chacha`.
+    ChaCha(ChaChaRng),
+}
+
+impl AnyRng {
+    /// Create the fastest available RNG, seeded from the operating system's
+    /// random-number generator.
+    #[cfg(feature = "getrandom")]
+    pub fn new_best() -> Result<AnyRng, ::getrandom::Error> {
+        let mut seed = [0; SEEDBYTES];
+        ::getrandom::getrandom(&mut seed)?;
+        Ok(AnyRng::from_seed(seed))
+    }
+}
+
+impl RngCore for AnyRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            AnyRng::Aes(rng) => rng.next_u32(),
+            AnyRng::ChaCha(rng) => rng.next_u32(),
+        }
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            AnyRng::Aes(rng) => rng.next_u64(),
+            AnyRng::ChaCha(rng) => rng.next_u64(),
+        }
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            AnyRng::Aes(rng) => rng.fill_bytes(dest),
+            AnyRng::ChaCha(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        match self {
+            AnyRng::Aes(rng) => rng.try_fill_bytes(dest),
+            AnyRng::ChaCha(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+impl SeedableRng for AnyRng {
+    type Seed = [u8; SEEDBYTES];
+
+    /// Create an `AesRng` if the AES instructions are available, and a
+    /// ChaCha20 RNG otherwise.
+    fn from_seed(seed: [u8; SEEDBYTES]) -> AnyRng {
+        if is_aes_available() {
+            AnyRng::Aes(AesRng::from_seed(seed))
+        } else {
+            AnyRng::ChaCha(ChaChaRng::from_seed(seed))
+        }
+    }
+}
+
+impl CryptoRng for AnyRng {}
+
+#[cfg(test)]
+mod tests {
+    use rand_chacha::ChaChaRng;
+    use rand_core::{RngCore, SeedableRng};
+
+    use AesRng;
+    use super::AnyRng;
+
+    #[test]
+    fn dispatch() {
+        let mut a = AnyRng::from_seed([1; 32]);
+        let mut b = AesRng::from_seed([1; 32]);
+        assert!(matches!(a, AnyRng::Aes(_)));
+        assert_eq!(a.next_u64(), b.next_u64());
+
+        let mut c = AnyRng::ChaCha(ChaChaRng::from_seed([1; 32]));
+        let mut d = ChaChaRng::from_seed([1; 32]);
+        let mut buf_c = [0; 40];
+        let mut buf_d = [0; 40];
+        c.fill_bytes(&mut buf_c);
+        d.fill_bytes(&mut buf_d);
+        assert_eq!(buf_c, buf_d);
+        assert_eq!(c.next_u32(), d.next_u32());
+    }
+}
@@ -0,0 +1,199 @@
+use core::arch::aarch64::*;
+
+use block::Block;
+
+/// Check whether the CPU supports the ARMv8 AES instructions.
+///
+/// If the `aes` target feature is enabled at compile time, it always returns
+/// `true`. Without the `std` feature, only the target feature is checked.
+#[inline]
+pub fn is_aes_available() -> bool {
+    if cfg!(target_feature = "aes") {
+        return true;
+    }
+    #[cfg(feature = "std")]
+    {
+        ::std::arch::is_aarch64_feature_detected!("aes")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        false
+    }
+}
+
+/// SIMD type used by the fast-key erasure RNG.
+///
+/// This provides the same abstraction over the relevant SIMD instructions as
+/// the x86 implementation, emulating the semantics of AES-NI.
+#[derive(Debug, Clone, Copy)]
+pub struct M128(pub uint8x16_t);
+
+impl M128 {
+    #[inline]
+    pub unsafe fn load(mem_addr: *const u8) -> M128 {
+        M128(vld1q_u8(mem_addr))
+    }
+
+    #[inline]
+    pub unsafe fn store(&self, mem_addr: *mut u8) {
+        vst1q_u8(mem_addr, self.0);
+    }
+
+    /// Like `store`, but `mem_addr` must be 16-byte aligned.
+    ///
+    /// There is no separate aligned store on AArch64.
+    #[inline]
+    pub unsafe fn store_aligned(&self, mem_addr: *mut u8) {
+        self.store(mem_addr);
+    }
+
+    #[inline]
+    pub fn bytes(&self) -> [u8; 16] {
+        Block::from_m128i(*self).0
+    }
+
+    /// Add as 128-bit little-endian integers, unlike `Add`, which adds the
+    /// upper and lower 64 bits separately.
+    #[inline]
+    pub fn add128(self, rhs: M128) -> M128 {
+        unsafe {
+            let a = vreinterpretq_u64_u8(self.0);
+            let b = vreinterpretq_u64_u8(rhs.0);
+            let sum = vaddq_u64(a, b);
+            // The most significant bit of `(a & b) | ((a | b) & !sum)` is the
+            // carry out of each half. Only the one of the lower half is kept,
+            // moved to the upper half.
+            let carry = vorrq_u64(vandq_u64(a, b), vbicq_u64(vorrq_u64(a, b), sum));
+            let carry = vextq_u64(vdupq_n_u64(0), vshrq_n_u64(carry, 63), 1);
+            M128(vreinterpretq_u8_u64(vaddq_u64(sum, carry)))
+        }
+    }
+
+    /// Equivalent to `_mm_aesenc_si128`.
+    ///
+    /// `vaeseq_u8` adds the round key before `SubBytes` and `ShiftRows`, so it
+    /// is called with a zero key and the round key is added at the end.
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn encrypt(self, round_key: M128) -> M128 {
+        M128(veorq_u8(vaesmcq_u8(vaeseq_u8(self.0, vdupq_n_u8(0))), round_key.0))
+    }
+
+    /// Equivalent to `_mm_aesenclast_si128`.
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn encrypt_last(self, round_key: M128) -> M128 {
+        M128(veorq_u8(vaeseq_u8(self.0, vdupq_n_u8(0)), round_key.0))
+    }
+
+    /// Equivalent to `_mm_aesdec_si128`.
+    ///
+    /// Like for `encrypt`, `vaesdq_u8` is called with a zero key.
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn decrypt(self, round_key: M128) -> M128 {
+        M128(veorq_u8(vaesimcq_u8(vaesdq_u8(self.0, vdupq_n_u8(0))), round_key.0))
+    }
+
+    /// Equivalent to `_mm_aesdeclast_si128`.
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn decrypt_last(self, round_key: M128) -> M128 {
+        M128(veorq_u8(vaesdq_u8(self.0, vdupq_n_u8(0)), round_key.0))
+    }
+
+    /// Equivalent to `_mm_aesimc_si128`.
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn inv_mix_columns(self) -> M128 {
+        M128(vaesimcq_u8(self.0))
+    }
+}
+
+impl ::core::ops::BitXor<M128> for M128 {
+    type Output = M128;
+
+    #[inline]
+    fn bitxor(self, rhs: M128) -> M128 {
+        unsafe { M128(veorq_u8(self.0, rhs.0)) }
+    }
+}
+
+// The following macros have to be used in an unsafe context.
+
+/// Equivalent to `_mm_slli_si128`.
+macro_rules! shiftl {
+    ($a:expr, $imm8:expr) => ({
+        use core::arch::aarch64::{vdupq_n_u8, vextq_u8};
+
+        let M128(a) = $a;
+        M128(vextq_u8(vdupq_n_u8(0), a, 16 - $imm8))
+    });
+}
+
+/// Equivalent to `_mm_shuffle_epi32`.
+macro_rules! shuffle {
+    ($a:expr, $imm8:expr) => ({
+        use core::arch::aarch64::{vld1q_u8, vqtbl1q_u8};
+
+        let M128(a) = $a;
+        let mut idx = [0u8; 16];
+        for (i, x) in idx.iter_mut().enumerate() {
+            *x = ((($imm8 >> (2 * (i / 4))) & 3) * 4 + i % 4) as u8;
+        }
+        M128(vqtbl1q_u8(a, vld1q_u8(idx.as_ptr())))
+    });
+}
+
+/// Equivalent to `_mm_aeskeygenassist_si128`.
+///
+/// `vaeseq_u8` with a zero key applies `SubBytes` and `ShiftRows`. The table
+/// undoes `ShiftRows` while selecting and rotating the words required by
+/// `aeskeygenassist`.
+macro_rules! keygenassist {
+    ($a:expr, $imm8:expr) => ({
+        use core::arch::aarch64::{vaeseq_u8, vdupq_n_u8, veorq_u8, vld1q_u8, vqtbl1q_u8};
+
+        const IDX: [u8; 16] = [4, 1, 14, 11, 1, 14, 11, 4, 12, 9, 6, 3, 9, 6, 3, 12];
+        let rc: u8 = $imm8;
+        let rcon = [0, 0, 0, 0, rc, 0, 0, 0, 0, 0, 0, 0, rc, 0, 0, 0];
+
+        let M128(a) = $a;
+        let t = vqtbl1q_u8(vaeseq_u8(a, vdupq_n_u8(0)), vld1q_u8(IDX.as_ptr()));
+        M128(veorq_u8(t, vld1q_u8(rcon.as_ptr())))
+    });
+}
+
+impl ::core::ops::Add<M128> for M128 {
+    type Output = M128;
+
+    #[inline]
+    fn add(self, rhs: M128) -> M128 {
+        unsafe {
+            M128(vreinterpretq_u8_u64(vaddq_u64(
+                vreinterpretq_u64_u8(self.0),
+                vreinterpretq_u64_u8(rhs.0),
+            )))
+        }
+    }
+}
+
+impl ::core::convert::From<(i64, i64)> for M128 {
+    /// Create from the upper and lower 64 bits, like `_mm_set_epi64x`.
+    #[inline]
+    fn from(x: (i64, i64)) -> M128 {
+        unsafe {
+            M128(vreinterpretq_u8_u64(vcombine_u64(
+                vcreate_u64(x.1 as u64),
+                vcreate_u64(x.0 as u64),
+            )))
+        }
+    }
+}
+
+impl ::core::convert::From<uint8x16_t> for M128 {
+    #[inline]
+    fn from(x: uint8x16_t) -> M128 {
+        M128(x)
+    }
+}
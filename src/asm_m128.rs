@@ -0,0 +1,166 @@
+//! `M128` for the backends using inline assembly, RISC-V and POWER.
+//!
+//! The state is kept in a `u128`, only the AES rounds use the vector unit.
+//! Every round loads the state and round key from memory and stores the
+//! result, and the blocks are processed one at a time. This is a slow
+//! reference path: a fast implementation would keep several blocks in vector
+//! registers across all rounds, which cannot be expressed using the
+//! per-round `M128` abstraction.
+//! The backend module defines `cipher_round!(op, state, round_key)`, which
+//! applies one AES round using the instruction for `op`:
+//!
+//! - `enc`: `SubBytes`, `ShiftRows`, `MixColumns` and `AddRoundKey`,
+//! - `enc_last`: like `enc`, without `MixColumns`,
+//! - `dec`: `InvShiftRows`, `InvSubBytes`, `AddRoundKey` and `InvMixColumns`,
+//! - `dec_last`: like `dec`, without `InvMixColumns`.
+//!
+//! The state and round key have the same byte order as the x86 AES state.
+
+use block::Block;
+
+/// SIMD type used by the fast-key erasure RNG.
+///
+/// This provides the same abstraction over the relevant SIMD instructions as
+/// the x86 implementation, emulating the semantics of AES-NI.
+#[derive(Debug, Clone, Copy)]
+pub struct M128(pub u128);
+
+impl M128 {
+    #[inline]
+    pub unsafe fn load(mem_addr: *const u8) -> M128 {
+        M128(u128::from_le_bytes((mem_addr as *const [u8; 16]).read_unaligned()))
+    }
+
+    #[inline]
+    pub unsafe fn store(&self, mem_addr: *mut u8) {
+        (mem_addr as *mut [u8; 16]).write_unaligned(self.0.to_le_bytes());
+    }
+
+    /// Like `store`, but `mem_addr` must be 16-byte aligned.
+    #[inline]
+    pub unsafe fn store_aligned(&self, mem_addr: *mut u8) {
+        self.store(mem_addr);
+    }
+
+    #[inline]
+    pub fn bytes(&self) -> [u8; 16] {
+        Block::from_m128i(*self).0
+    }
+
+    /// Add as 128-bit little-endian integers, unlike `Add`, which adds the
+    /// upper and lower 64 bits separately.
+    #[inline]
+    pub fn add128(self, rhs: M128) -> M128 {
+        M128(self.0.wrapping_add(rhs.0))
+    }
+
+    /// Equivalent to `_mm_aesenc_si128`.
+    #[inline]
+    pub unsafe fn encrypt(self, round_key: M128) -> M128 {
+        cipher_round!(enc, self, round_key)
+    }
+
+    /// Equivalent to `_mm_aesenclast_si128`.
+    #[inline]
+    pub unsafe fn encrypt_last(self, round_key: M128) -> M128 {
+        cipher_round!(enc_last, self, round_key)
+    }
+
+    /// Equivalent to `_mm_aesdec_si128`.
+    ///
+    /// `dec` adds the round key before `InvMixColumns`, so it is called with a
+    /// zero key and the round key is added at the end.
+    #[inline]
+    pub unsafe fn decrypt(self, round_key: M128) -> M128 {
+        cipher_round!(dec, self, M128(0)) ^ round_key
+    }
+
+    /// Equivalent to `_mm_aesdeclast_si128`.
+    #[inline]
+    pub unsafe fn decrypt_last(self, round_key: M128) -> M128 {
+        cipher_round!(dec_last, self, round_key)
+    }
+
+    /// Equivalent to `_mm_aesimc_si128`.
+    ///
+    /// There is no instruction for `InvMixColumns` alone. `enc_last` applies
+    /// `SubBytes` and `ShiftRows`, which are undone by `dec` before applying
+    /// `InvMixColumns`.
+    #[inline]
+    pub unsafe fn inv_mix_columns(self) -> M128 {
+        cipher_round!(dec, cipher_round!(enc_last, self, M128(0)), M128(0))
+    }
+}
+
+impl ::core::ops::BitXor<M128> for M128 {
+    type Output = M128;
+
+    #[inline]
+    fn bitxor(self, rhs: M128) -> M128 {
+        M128(self.0 ^ rhs.0)
+    }
+}
+
+// The following macros have to be used in an unsafe context.
+
+/// Equivalent to `_mm_slli_si128`.
+macro_rules! shiftl {
+    ($a:expr, $imm8:expr) => ({
+        let M128(a) = $a;
+        M128(a << (8 * $imm8))
+    });
+}
+
+/// Equivalent to `_mm_shuffle_epi32`.
+macro_rules! shuffle {
+    ($a:expr, $imm8:expr) => ({
+        let M128(a) = $a;
+        let mut r = 0u128;
+        for i in 0..4 {
+            let word = (a >> (32 * (($imm8 >> (2 * i)) & 3))) as u32;
+            r |= (word as u128) << (32 * i);
+        }
+        M128(r)
+    });
+}
+
+/// Equivalent to `_mm_aeskeygenassist_si128`.
+///
+/// `enc_last` with a zero key applies `SubBytes` and `ShiftRows`. The table
+/// undoes `ShiftRows` while selecting and rotating the words required by
+/// `aeskeygenassist`.
+macro_rules! keygenassist {
+    ($a:expr, $imm8:expr) => ({
+        const IDX: [usize; 16] = [4, 1, 14, 11, 1, 14, 11, 4, 12, 9, 6, 3, 9, 6, 3, 12];
+        let rc: u8 = $imm8;
+
+        let t = $a.encrypt_last(M128(0)).bytes();
+        let mut r = [0u8; 16];
+        for (x, &i) in r.iter_mut().zip(IDX.iter()) {
+            *x = t[i];
+        }
+        r[4] ^= rc;
+        r[12] ^= rc;
+        M128(u128::from_le_bytes(r))
+    });
+}
+
+impl ::core::ops::Add<M128> for M128 {
+    type Output = M128;
+
+    /// Add the upper and lower 64 bits separately, like `_mm_add_epi64`.
+    #[inline]
+    fn add(self, rhs: M128) -> M128 {
+        let lo = (self.0 as u64).wrapping_add(rhs.0 as u64);
+        let hi = ((self.0 >> 64) as u64).wrapping_add((rhs.0 >> 64) as u64);
+        M128(((hi as u128) << 64) | lo as u128)
+    }
+}
+
+impl ::core::convert::From<(i64, i64)> for M128 {
+    /// Create from the upper and lower 64 bits, like `_mm_set_epi64x`.
+    #[inline]
+    fn from(x: (i64, i64)) -> M128 {
+        M128(((x.0 as u64 as u128) << 64) | x.1 as u64 as u128)
+    }
+}
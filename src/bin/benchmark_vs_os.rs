@@ -0,0 +1,63 @@
+//! Compare the throughput of `AesRng` with reading from `/dev/urandom`.
+//!
+//! Run it with `cargo run --release --bin benchmark_vs_os`.
+
+extern crate aesrng;
+
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use std::time::{Duration, Instant};
+
+use aesrng::AesRng;
+use aesrng::rand_core::{RngCore, SeedableRng};
+
+/// Total number of bytes generated by each source.
+const TOTAL: usize = 100 << 20;
+/// Size of the buffer filled in each iteration.
+const CHUNK: usize = 1 << 20;
+
+/// Return the time it takes to fill `TOTAL` bytes using `fill`.
+fn measure<F: FnMut(&mut [u8])>(mut fill: F) -> Duration {
+    let mut buf = vec![0; CHUNK];
+    let start = Instant::now();
+    for _ in 0..TOTAL / CHUNK {
+        fill(&mut buf);
+    }
+    start.elapsed()
+}
+
+/// Convert the time for generating `TOTAL` bytes into GB/s.
+fn throughput(elapsed: Duration) -> f64 {
+    TOTAL as f64 / elapsed.as_secs_f64() / 1e9
+}
+
+fn main() {
+    let mut urandom = match File::open("/dev/urandom") {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("failed to open /dev/urandom: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut seed = [0; 32];
+    if let Err(e) = urandom.read_exact(&mut seed) {
+        eprintln!("failed to read from /dev/urandom: {}", e);
+        process::exit(1);
+    }
+
+    let mut rng = AesRng::from_seed(seed);
+    let aes = measure(|buf| rng.fill_bytes(buf));
+    let mut error = None;
+    let os = measure(|buf| {
+        if let Err(e) = urandom.read_exact(buf) {
+            error = Some(e);
+        }
+    });
+    if let Some(e) = error {
+        eprintln!("failed to read from /dev/urandom: {}", e);
+        process::exit(1);
+    }
+
+    println!("AesRng: {:.1} GB/s, /dev/urandom: {:.1} GB/s", throughput(aes), throughput(os));
+}
@@ -0,0 +1,39 @@
+use M128;
+
+/// A 16 byte block with the alignment of `M128`.
+///
+/// This allows to convert between bytes and `M128` without `unsafe`.
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Block(pub [u8; 16]);
+
+impl Block {
+    /// Create a block from the first 16 bytes of the given slice.
+    ///
+    /// Panics if the slice is shorter than 16 bytes.
+    #[inline]
+    pub fn from_slice(bytes: &[u8]) -> Block {
+        let mut block = Block::default();
+        block.0.copy_from_slice(&bytes[..16]);
+        block
+    }
+
+    #[inline]
+    pub fn from_m128i(x: M128) -> Block {
+        let mut block = Block::default();
+        unsafe { x.store_aligned(block.0.as_mut_ptr()) };
+        block
+    }
+
+    #[inline]
+    pub fn to_m128i(self) -> M128 {
+        unsafe { M128::load(self.0.as_ptr()) }
+    }
+}
+
+impl From<[u8; 16]> for Block {
+    #[inline]
+    fn from(bytes: [u8; 16]) -> Block {
+        Block(bytes)
+    }
+}
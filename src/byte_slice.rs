@@ -1,7 +1,7 @@
 /// Trait for casting types to byte slices.
 pub trait AsByteSliceMut {
     /// Return a mutable reference to self as a byte slice
-    fn as_byte_slice_mut<'a>(&'a mut self) -> &'a mut [u8];
+    fn as_byte_slice_mut(&mut self) -> &mut [u8];
 
     /// Call `to_le` on each element (i.e. byte-swap on Big Endian platforms).
     fn to_le(&mut self);
@@ -9,7 +9,7 @@ pub trait AsByteSliceMut {
 
 impl AsByteSliceMut for [u8] {
     #[inline]
-    fn as_byte_slice_mut<'a>(&'a mut self) -> &'a mut [u8] {
+    fn as_byte_slice_mut(&mut self) -> &mut [u8] {
         self
     }
 
@@ -21,12 +21,14 @@ macro_rules! impl_as_byte_slice {
     ($t:ty) => {
         impl AsByteSliceMut for [$t] {
             #[inline]
-            fn as_byte_slice_mut<'a>(&'a mut self) -> &'a mut [u8] {
+            fn as_byte_slice_mut(&mut self) -> &mut [u8] {
+                if self.is_empty() {
+                    return &mut [];
+                }
                 unsafe {
-                    ::std::slice::from_raw_parts_mut(&mut self[0]
-                        as *mut $t
+                    ::core::slice::from_raw_parts_mut(self.as_mut_ptr()
                         as *mut u8,
-                        self.len() * ::std::mem::size_of::<$t>()
+                        self.len() * ::core::mem::size_of::<$t>()
                     )
                 }
             }
@@ -41,4 +43,15 @@ macro_rules! impl_as_byte_slice {
     }
 }
 
+impl_as_byte_slice!(u16);
 impl_as_byte_slice!(u32);
+impl_as_byte_slice!(u64);
+impl_as_byte_slice!(u128);
+impl_as_byte_slice!(i8);
+impl_as_byte_slice!(i16);
+impl_as_byte_slice!(i32);
+impl_as_byte_slice!(i64);
+impl_as_byte_slice!(i128);
+// The output for these depends on the pointer width of the platform.
+impl_as_byte_slice!(usize);
+impl_as_byte_slice!(isize);
@@ -0,0 +1,133 @@
+//! `AesCore` with a 128-bit counter.
+
+use core::fmt;
+
+use rand_core::SeedableRng;
+use rand_core::block::BlockRngCore;
+
+use {AesCore, M128, AESRNG_BUFSIZE};
+use byte_slice::AsByteSliceMut;
+
+/// A fast-key-erasure random-number generator using a 128-bit counter.
+///
+/// `AesCore` only increments the lower 64 bits of the counter, so the output
+/// repeats after 2^64 blocks unless the key is erased in between. This variant
+/// carries into the upper 64 bits, at the cost of a lower throughput.
+#[derive(Clone)]
+pub struct AesCore128BitCounter(AesCore);
+
+impl AesCore128BitCounter {
+    /// Fill the given buffer with random data.
+    ///
+    /// Erases the key after filling the buffer.
+    #[inline]
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        // An `AesCore` can only be created if the AES instructions are
+        // available.
+        unsafe { self.fill_impl(buffer) }
+    }
+
+    /// Return the current value of the counter.
+    #[inline]
+    #[must_use]
+    pub fn get_counter(&self) -> u128 {
+        self.0.get_counter()
+    }
+
+    /// Set the counter, jumping to an arbitrary position in the stream.
+    #[inline]
+    pub fn set_counter(&mut self, position: u128) {
+        self.0.set_counter(position)
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_impl(&mut self, buffer: &mut [u8]) {
+        let core = &mut self.0;
+        fill!(compute_rounds, store, M128::add128, core.round_keys, core.counter, buffer);
+        core.erase_key();
+    }
+}
+
+impl From<AesCore> for AesCore128BitCounter {
+    fn from(core: AesCore) -> AesCore128BitCounter {
+        AesCore128BitCounter(core)
+    }
+}
+
+impl SeedableRng for AesCore128BitCounter {
+    type Seed = <AesCore as SeedableRng>::Seed;
+
+    /// Create a new `AesCore128BitCounter` using the given seed.
+    ///
+    /// Panics if the AES instructions are not available.
+    fn from_seed(seed: Self::Seed) -> AesCore128BitCounter {
+        AesCore128BitCounter(AesCore::from_seed(seed))
+    }
+}
+
+// Custom Debug implementation that does not expose the internal state
+impl fmt::Debug for AesCore128BitCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AesCore128BitCounter {{}}")
+    }
+}
+
+impl BlockRngCore for AesCore128BitCounter {
+    type Item = u32;
+    type Results = [u32; AESRNG_BUFSIZE];
+
+    #[inline(always)]
+    fn generate(&mut self, results: &mut Self::Results) {
+        self.fill(results.as_byte_slice_mut());
+        results.to_le();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::SeedableRng;
+
+    use {AesCore, Block};
+    use super::AesCore128BitCounter;
+
+    #[test]
+    fn add128() {
+        let values = [
+            0, 1, u64::MAX as u128, 1 << 64, 1 << 63, u128::MAX, u128::MAX - 1,
+            0x8000_0000_0000_0001_7fff_ffff_ffff_ffff,
+        ];
+        for &a in &values {
+            for &b in &values {
+                let sum = Block(a.to_le_bytes()).to_m128i().add128(Block(b.to_le_bytes()).to_m128i());
+                assert_eq!(u128::from_le_bytes(sum.bytes()), a.wrapping_add(b));
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_aes_core() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = AesCore128BitCounter::from(a.clone());
+        let mut buf_a = vec![0; 300];
+        let mut buf_b = vec![0; 300];
+        a.fill(&mut buf_a);
+        b.fill(&mut buf_b);
+        assert_eq!(buf_a, buf_b);
+    }
+
+    #[test]
+    fn carry() {
+        let mut core = AesCore::from_seed([1; 32]);
+        core.set_counter(u64::MAX as u128);
+        let mut wide = AesCore128BitCounter::from(core.clone());
+        let mut buf = [0; 300];
+        wide.fill(&mut buf);
+        assert_eq!(wide.get_counter(), (1 << 64) + 18);
+
+        // The second block uses the carried counter.
+        let mut expected = [0; 16];
+        core.set_counter(1 << 64);
+        core.fill(&mut expected);
+        assert_eq!(buf[16..32], expected);
+    }
+}
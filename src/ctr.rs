@@ -0,0 +1,139 @@
+//! AES128 in counter mode, usable as a stream cipher.
+
+use core::fmt;
+
+use rand_core::SeedableRng;
+
+use {AesCore, Block, M128, encrypt_block};
+
+/// AES128 in counter mode (CTR), as specified in NIST SP 800-38A.
+///
+/// Unlike `AesCore`, this does not erase the key and uses the plain AES block
+/// cipher, so it is compatible with other AES-CTR implementations. The whole
+/// 128-bit counter block is incremented as a big-endian integer.
+///
+/// A nonce must never be reused with the same key, otherwise the keystream
+/// repeats and the plaintexts can be recovered.
+#[derive(Clone)]
+pub struct AesCtr {
+    core: AesCore,
+    keystream: [u8; 16],
+    used: usize,
+}
+
+impl AesCtr {
+    /// Create a new `AesCtr` using the given key and initial counter block.
+    ///
+    /// Panics if the AES instructions are not available.
+    #[must_use]
+    pub fn new(key: &[u8; 16], nonce: &[u8; 16]) -> AesCtr {
+        let mut seed = [0; 32];
+        seed[..16].copy_from_slice(key);
+        seed[16..].copy_from_slice(nonce);
+        AesCtr {
+            core: AesCore::from_seed(seed),
+            keystream: [0; 16],
+            used: 16,
+        }
+    }
+
+    /// XOR the keystream into the given data, encrypting or decrypting it.
+    ///
+    /// Applying the keystream in several calls gives the same result as
+    /// applying it at once.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        // An `AesCtr` can only be created if the AES instructions are
+        // available.
+        unsafe { self.apply_keystream_impl(data) }
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn apply_keystream_impl(&mut self, data: &mut [u8]) {
+        let n = data.len().min(16 - self.used);
+        let (head, data) = data.split_at_mut(n);
+        for (x, k) in head.iter_mut().zip(&self.keystream[self.used..]) {
+            *x ^= k;
+        }
+        self.used += n;
+
+        let mut chunks = data.chunks_exact_mut(16);
+        for chunk in &mut chunks {
+            let c = Block::from_slice(chunk).to_m128i() ^ self.next_block();
+            c.store(chunk.as_mut_ptr());
+        }
+        let tail = chunks.into_remainder();
+        if !tail.is_empty() {
+            self.keystream = self.next_block().bytes();
+            for (x, k) in tail.iter_mut().zip(&self.keystream) {
+                *x ^= k;
+            }
+            self.used = tail.len();
+        }
+    }
+
+    /// Encrypt the current counter block and increment the counter.
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn next_block(&mut self) -> M128 {
+        let r = encrypt_block(&self.core.round_keys, self.core.counter);
+        let counter = u128::from_be_bytes(self.core.counter.bytes()).wrapping_add(1);
+        self.core.counter = Block(counter.to_be_bytes()).to_m128i();
+        r
+    }
+}
+
+// Custom Debug implementation that does not expose the internal state
+impl fmt::Debug for AesCtr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AesCtr {{}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AesCtr;
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+    const NONCE: [u8; 16] = [
+        0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe,
+        0xff,
+    ];
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn sp800_38a() {
+        // Test vector F.5.1 from NIST SP 800-38A.
+        let mut data = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710");
+        let expected = hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee");
+        AesCtr::new(&KEY, &NONCE).apply_keystream(&mut data);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn chunks() {
+        let mut expected = vec![0; 100];
+        AesCtr::new(&KEY, &NONCE).apply_keystream(&mut expected);
+
+        let mut ctr = AesCtr::new(&KEY, &NONCE);
+        let mut data = vec![0; 100];
+        for chunk in data.chunks_mut(7) {
+            ctr.apply_keystream(chunk);
+        }
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let plaintext = b"attack at dawn, attack at dawn!!!".to_vec();
+        let mut data = plaintext.clone();
+        AesCtr::new(&KEY, &NONCE).apply_keystream(&mut data);
+        assert_ne!(data, plaintext);
+        AesCtr::new(&KEY, &NONCE).apply_keystream(&mut data);
+        assert_eq!(data, plaintext);
+    }
+}
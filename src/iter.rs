@@ -0,0 +1,169 @@
+//! Iterators over random integers.
+
+use rand_core::RngCore;
+
+use {AesCore, AesRng};
+
+/// An infinite iterator over random `u64` values.
+///
+/// Created by `AesRng::iter_u64`.
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct AesRngIter<'a>(pub(crate) &'a mut AesRng);
+
+impl<'a> AesRngIter<'a> {
+    /// Limit the iterator to `n` values.
+    pub fn limit(self, n: usize) -> Limit<AesRngIter<'a>> {
+        Limit { iter: self, remaining: n }
+    }
+}
+
+impl<'a> Iterator for AesRngIter<'a> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        Some(self.0.next_u64())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// An infinite iterator over random `u32` values.
+///
+/// Created by `AesRng::iter_u32`.
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct AesRngIterU32<'a>(pub(crate) &'a mut AesRng);
+
+impl<'a> AesRngIterU32<'a> {
+    /// Limit the iterator to `n` values.
+    pub fn limit(self, n: usize) -> Limit<AesRngIterU32<'a>> {
+        Limit { iter: self, remaining: n }
+    }
+}
+
+impl<'a> Iterator for AesRngIterU32<'a> {
+    type Item = u32;
+
+    #[inline]
+    fn next(&mut self) -> Option<u32> {
+        Some(self.0.next_u32())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// An infinite iterator over random 16-byte blocks.
+///
+/// Created by `AesCore::block_iter`.
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct BlockIter<'a>(pub(crate) &'a mut AesCore, pub(crate) [u8; 16]);
+
+impl<'a> BlockIter<'a> {
+    /// Limit the iterator to `n` blocks.
+    pub fn limit(self, n: usize) -> Limit<BlockIter<'a>> {
+        Limit { iter: self, remaining: n }
+    }
+}
+
+impl<'a> Iterator for BlockIter<'a> {
+    type Item = [u8; 16];
+
+    #[inline]
+    fn next(&mut self) -> Option<[u8; 16]> {
+        self.0.fill(&mut self.1);
+        Some(self.1)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// A finite iterator over random values, yielding a fixed number of them.
+///
+/// Unlike `Iterator::take`, this implements `ExactSizeIterator`.
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Limit<I> {
+    iter: I,
+    remaining: usize,
+}
+
+impl<I: Iterator> Iterator for Limit<I> {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for Limit<I> {}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::{RngCore, SeedableRng};
+
+    use {AesCore, AesRng};
+
+    #[test]
+    fn block_iter() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = AesCore::from_seed([1; 32]);
+        for block in a.block_iter().limit(10) {
+            let mut expected = [0; 16];
+            b.fill(&mut expected);
+            assert_eq!(block, expected);
+        }
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn iter_u64() {
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = AesRng::from_seed([1; 32]);
+        let values: Vec<u64> = a.iter_u64().take(100).collect();
+        for &x in &values {
+            assert_eq!(x, b.next_u64());
+        }
+    }
+
+    #[test]
+    fn iter_u32() {
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = AesRng::from_seed([1; 32]);
+        let values: Vec<u32> = a.iter_u32().take(100).collect();
+        for &x in &values {
+            assert_eq!(x, b.next_u32());
+        }
+    }
+
+    #[test]
+    fn limit() {
+        let mut rng = AesRng::from_seed([1; 32]);
+        let mut iter = rng.iter_u64().limit(3);
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.count(), 2);
+    }
+}
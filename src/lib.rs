@@ -2,37 +2,192 @@
 //!
 //! This is port of https://github.com/jedisct1/aes-stream.
 //!
-//! This crate does not implement any software fallback and does not
-//! automatically check CPUID, so if you are using this crate make sure to run
-//! software on an appropriate hardware or to use software fallback with runtime
-//! detection of AES-NI availability (e.g. by using the
-//! [`cupid`](https://crates.io/crates/cupid) crate).
+//! On AArch64, the ARMv8 cryptography extension is used instead, providing
+//! the same API and output.
 //!
-//! When using this crate do not forget to enable `aes` target feature,
-//! otherwise you will get an empty crate. You can do it either by using
-//! `RUSTFLAGS="-C target-feature=+aes"` or by editing your `.cargo/config`.
-#![cfg(any(target_arch = "x86_64", target_arch = "x86"))]
-#![cfg(target_feature = "aes")]
+//! On RISC-V, the vector AES extension (Zvkned) is supported if the `v` and
+//! `zvkned` target features are enabled at compile time. On 64-bit POWER,
+//! the POWER8 AES instructions are supported if the `power8-crypto` and
+//! `power9-vector` target features are enabled at compile time, for example
+//! with `-C target-cpu=pwr9`. Both currently require a nightly compiler. These
+//! backends are experimental: they are only checked to compile, and have not
+//! been tested on hardware or an emulator.
+//!
+//! The RNGs use AES128 (`AesRng::KEY_BITS`) in counter mode, generating
+//! blocks of `AesCore::OUTPUT_BLOCK_BYTES` bytes. Only the lower
+//! `AesCore::PERIOD_BITS` = 64 bits of the counter are incremented, so the
+//! keystream of a single key has a period of 2^64 blocks. After each `fill`,
+//! the key is replaced by new output of the RNG (fast key erasure), which
+//! starts a new keystream. The period therefore only limits the size of a
+//! single buffer, which is far below 2^64 blocks in practice.
+//!
+//! With the `getrandom` feature, `AesRng::from_entropy` seeds the RNG from the
+//! operating system's random-number generator. This is the recommended way to
+//! create an `AesRng` in security-sensitive contexts:
+//!
+//! ```
+//! # #[cfg(feature = "getrandom")] {
+//! use aesrng::AesRng;
+//! use aesrng::rand_core::RngCore;
+//!
+//! let mut rng = AesRng::from_entropy().expect("failed to get entropy");
+//! let mut buf = [0; 1024];
+//! rng.fill_bytes(&mut buf);
+//! # }
+//! ```
+//!
+//! The `wasm` feature enables `getrandom`'s JavaScript backend, which is
+//! required for seeding on `wasm32-unknown-unknown`. Note that WebAssembly
+//! has no AES instructions, so the RNGs themselves are only available on x86
+//! and AArch64; on other targets this crate is empty.
+//!
+//! Except for `AnyRng`, this crate does not implement any software fallback.
+//! The availability of AES-NI is checked at runtime via CPUID when creating
+//! an RNG, which panics if the CPU does not support it. Use
+//! `is_aes_ni_available` or `AesRng::new_if_available` to select a fallback
+//! instead.
+//!
+//! With the `chacha` feature, `AnyRng` uses ChaCha20 as a software fallback
+//! if the AES instructions are not available at runtime.
+//!
+//! For best performance, enable the `aes` target feature. This removes the
+//! runtime check and allows the AES-NI instructions to be inlined. You can do
+//! it either by using `RUSTFLAGS="-C target-feature=+aes"` or by editing your
+//! `.cargo/config`.
+//!
+//! The crate supports `no_std` when disabling the default `std` feature. The
+//! `alloc` feature enables `AesCore::fill_vec`. Without `std`, the AES
+//! instructions are only detected at runtime on x86, and the VAES fast path,
+//! `io::Read`, the `std::error::Error` impls, `SharedAesRng` and
+//! `thread_local_rng` are not available.
+//!
+//! The `vaes` feature enables a fast path for large buffers on x86 CPUs
+//! supporting VAES, which is detected at runtime. It requires the `std`
+//! feature and Rust 1.89 or greater.
+//!
+//! With the `zeroize` feature, the key material and the buffered output are
+//! erased when the RNGs are dropped.
+//!
+//! With the `memlock` feature, `LockedBox` keeps an `AesCore` in memory that
+//! is locked using `mlock` on Unix, so the key is not swapped to disk.
+//!
+//! With the `aead` feature, `aead::seal` and `aead::open` provide AES256-GCM
+//! authenticated encryption.
+//!
+//! With the `ocb` feature, `ocb::ocb_seal` and `ocb::ocb_open` provide
+//! AES128-OCB3 authenticated encryption using the key of an `AesCore`.
+//!
+//! With the `kdf` feature, an `AesCore` can be created from key material of
+//! any length using `TryFrom<&[u8]>`, which derives the seed using
+//! HKDF-SHA256.
+//!
+//! With the `rayon` feature, `parallel_fill` fills large buffers using
+//! multiple threads.
+//!
+//! With the `passphrase` feature, `AesCore::from_passphrase` derives the seed
+//! from a passphrase using PBKDF2-HMAC-SHA256.
+//!
+//! With the `subtle` feature, `AesCore`s are compared in constant time.
+//!
+//! With the `tracing` feature, the key expansion when seeding and the key
+//! erasure after `AesCore::fill` are logged as `DEBUG` events with the target
+//! `aesrng::key_erasure`.
+//!
+//! With the `serde1` feature, the state of `AesCore` and `AesRng` can be
+//! serialized and deserialized using Serde. The serialized state includes a
+//! format version, so states from incompatible versions of this crate are
+//! rejected, see `AesCore::try_deserialize`.
+#![cfg(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64",
+    all(target_arch = "riscv64", target_feature = "zvkned"),
+    all(target_arch = "powerpc64", target_feature = "power8-crypto", target_feature = "power9-vector"),
+))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-extern crate rand_core;
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "alloc")]
+#[macro_use]
+extern crate alloc;
+pub extern crate rand_core;
+#[cfg(feature = "chacha")]
+extern crate rand_chacha;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "getrandom")]
+extern crate getrandom;
+#[cfg(feature = "kdf")]
+extern crate hkdf;
+#[cfg(feature = "passphrase")]
+extern crate pbkdf2;
+#[cfg(all(feature = "memlock", unix))]
+extern crate libc;
+#[cfg(feature = "serde1")]
+#[macro_use]
+extern crate serde;
+#[cfg(any(feature = "kdf", feature = "passphrase"))]
+extern crate sha2;
+#[cfg(feature = "subtle")]
+extern crate subtle;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
 
-use std::fmt;
-use std::mem::MaybeUninit;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 use rand_core::{CryptoRng, RngCore, SeedableRng, Error};
 use rand_core::block::{BlockRngCore, BlockRng};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+mod block;
 mod byte_slice;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
 #[macro_use]
 mod simd;
+#[cfg(target_arch = "aarch64")]
+#[macro_use]
+mod arm;
+#[cfg(target_arch = "riscv64")]
+#[macro_use]
+mod riscv;
+#[cfg(target_arch = "powerpc64")]
+#[macro_use]
+mod powerpc;
+#[cfg(any(target_arch = "riscv64", target_arch = "powerpc64"))]
+#[macro_use]
+mod asm_m128;
 
-use byte_slice::AsByteSliceMut;
-use simd::M128;
+use block::Block;
+pub use byte_slice::AsByteSliceMut;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use simd::{M128, is_aes_ni_available as is_aes_available};
+#[cfg(target_arch = "aarch64")]
+use arm::M128;
+#[cfg(any(target_arch = "riscv64", target_arch = "powerpc64"))]
+use asm_m128::M128;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+pub use simd::is_aes_ni_available;
+#[cfg(target_arch = "aarch64")]
+pub use arm::is_aes_available;
+#[cfg(target_arch = "riscv64")]
+pub use riscv::is_aes_available;
+#[cfg(target_arch = "powerpc64")]
+pub use powerpc::is_aes_available;
 
 
 const AESRNG_BUFSIZE: usize = 32;
 
-// This is for AES128. AES256 is not implemented for now.
+// This is for AES128. See `aes256` for AES256.
 const ROUNDS: usize = 10;
 const SEEDBYTES: usize = 32;
 
@@ -48,7 +203,8 @@ macro_rules! drc {
     );
 }
 
-fn aes_key_expand_128(round_keys: &mut RoundKeys, mut t: M128) {
+#[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+unsafe fn aes_key_expand_128(round_keys: &mut RoundKeys, mut t: M128) {
     let mut s: M128;
     drc!(0, 1, s, t, round_keys);
     drc!(1, 2, s, t, round_keys);
@@ -63,6 +219,21 @@ fn aes_key_expand_128(round_keys: &mut RoundKeys, mut t: M128) {
     round_keys[10] = t;
 }
 
+/// Encrypt a single block using the plain AES block cipher.
+///
+/// The number of rounds is given by the number of round keys, so this works
+/// for AES128 and AES256. Unlike `compute_rounds!`, this does not feed
+/// forward the state.
+#[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+unsafe fn encrypt_block(round_keys: &[M128], block: M128) -> M128 {
+    let last = round_keys.len() - 1;
+    let mut r = block ^ round_keys[0];
+    for &key in &round_keys[1..last] {
+        r = r.encrypt(key);
+    }
+    r.encrypt_last(round_keys[last])
+}
+
 macro_rules! compute_rounds {
     ($n:expr, $c:ident, $r:ident, $s:ident, $round_keys:expr) => (
         $r[$n] = ($c[$n] ^ $round_keys[0]).encrypt($round_keys[1]);
@@ -75,245 +246,2750 @@ macro_rules! compute_rounds {
     );
 }
 
-/// A fast-key-erasure random-number generator using AES-NI.
-///
-/// This is designed to fill large buffers quickly with random data.
-#[repr(align(16))]
-#[derive(Clone)]
-pub struct AesCore {
-    round_keys: RoundKeys,
-    counter: M128,
+/// Overwrite the given blocks with zeros, in a way that cannot be optimized
+/// away.
+fn erase(blocks: &mut [M128]) {
+    let zero = M128::from((0, 0));
+    for block in blocks {
+        unsafe { ::core::ptr::write_volatile(block, zero) };
+    }
+    ::core::sync::atomic::compiler_fence(::core::sync::atomic::Ordering::SeqCst);
 }
 
-impl AesCore {
-    /// Fill the given buffer with random data.
-    ///
-    /// Erases the key after filling the buffer.
-    #[inline]
-    pub fn fill(&mut self, buffer: &mut [u8]) {
+/// Fill `$buffer` with the output of `$compute_rounds` in counter mode,
+/// starting at `$counter` and advancing it past the last block used.
+///
+/// Full blocks are written using `M128::$store`. The counter is incremented
+/// using `$add`, which defaults to adding the lower 64 bits only. With
+/// `@scratch`, the given arrays of 8 blocks are used for the counters and the
+/// intermediate state instead of local arrays.
+///
+/// The main loop processes 8 blocks at a time. Processing 16 blocks at a time
+/// did not improve throughput measurably (see the `fill_unroll` benchmark),
+/// and spills registers on targets with only 8 SIMD registers.
+macro_rules! fill {
+    ($compute_rounds:ident, $store:ident, $round_keys:expr, $counter:expr, $buffer:expr) => (
+        fill!($compute_rounds, $store, ::core::ops::Add::add, $round_keys, $counter, $buffer)
+    );
+    ($compute_rounds:ident, $store:ident, $add:expr, $round_keys:expr, $counter:expr, $buffer:expr) => ({
         let zero = M128::from((0, 0));
-        let one = M128::from((0, 1));
-        let two = M128::from((0, 2));
         let mut c = [zero; 8];
         let mut r = [zero; 8];
         let mut s = [zero; 8];
-
-        c[0] = self.counter;
+        fill!(@scratch c, r, s, $compute_rounds, $store, $add, $round_keys, $counter, $buffer)
+    });
+    (@scratch $c:ident, $r:ident, $s:ident, $compute_rounds:ident, $store:ident, $add:expr, $round_keys:expr, $counter:expr, $buffer:expr) => ({
+        let one = M128::from((0, 1));
+        let two = M128::from((0, 2));
+        $c[0] = $counter;
+        let buffer: &mut [u8] = $buffer;
         let mut remaining = buffer.len();
         let mut buffer = buffer.as_mut_ptr();
         while remaining > 128 {
-            c[1] = c[0] + one;
-            c[2] = c[0] + two;
-            c[3] = c[2] + one;
-            c[4] = c[2] + two;
-            c[5] = c[4] + one;
-            c[6] = c[4] + two;
-            c[7] = c[6] + one;
-            compute_rounds!(0, c, r, s, self.round_keys);
-            compute_rounds!(1, c, r, s, self.round_keys);
-            compute_rounds!(2, c, r, s, self.round_keys);
-            compute_rounds!(3, c, r, s, self.round_keys);
-            compute_rounds!(4, c, r, s, self.round_keys);
-            compute_rounds!(5, c, r, s, self.round_keys);
-            compute_rounds!(6, c, r, s, self.round_keys);
-            compute_rounds!(7, c, r, s, self.round_keys);
-            c[0] = c[7] + one;
+            $c[1] = ($add)($c[0], one);
+            $c[2] = ($add)($c[0], two);
+            $c[3] = ($add)($c[2], one);
+            $c[4] = ($add)($c[2], two);
+            $c[5] = ($add)($c[4], one);
+            $c[6] = ($add)($c[4], two);
+            $c[7] = ($add)($c[6], one);
+            $compute_rounds!(0, $c, $r, $s, $round_keys);
+            $compute_rounds!(1, $c, $r, $s, $round_keys);
+            $compute_rounds!(2, $c, $r, $s, $round_keys);
+            $compute_rounds!(3, $c, $r, $s, $round_keys);
+            $compute_rounds!(4, $c, $r, $s, $round_keys);
+            $compute_rounds!(5, $c, $r, $s, $round_keys);
+            $compute_rounds!(6, $c, $r, $s, $round_keys);
+            $compute_rounds!(7, $c, $r, $s, $round_keys);
+            $c[0] = ($add)($c[7], one);
             unsafe {
-                r[0].store(buffer.offset(0));
-                r[1].store(buffer.offset(16));
-                r[2].store(buffer.offset(32));
-                r[3].store(buffer.offset(48));
-                r[4].store(buffer.offset(64));
-                r[5].store(buffer.offset(80));
-                r[6].store(buffer.offset(96));
-                r[7].store(buffer.offset(112));
+                $r[0].$store(buffer.offset(0));
+                $r[1].$store(buffer.offset(16));
+                $r[2].$store(buffer.offset(32));
+                $r[3].$store(buffer.offset(48));
+                $r[4].$store(buffer.offset(64));
+                $r[5].$store(buffer.offset(80));
+                $r[6].$store(buffer.offset(96));
+                $r[7].$store(buffer.offset(112));
                 buffer = buffer.offset(128);
             }
             remaining -= 128;
         }
         while remaining > 32 {
-            c[1] = c[0] + one;
-            compute_rounds!(0, c, r, s, self.round_keys);
-            compute_rounds!(1, c, r, s, self.round_keys);
-            c[0] = c[1] + one;
+            $c[1] = ($add)($c[0], one);
+            $compute_rounds!(0, $c, $r, $s, $round_keys);
+            $compute_rounds!(1, $c, $r, $s, $round_keys);
+            $c[0] = ($add)($c[1], one);
             unsafe {
-                r[0].store(buffer.offset(0));
-                r[1].store(buffer.offset(16));
+                $r[0].$store(buffer.offset(0));
+                $r[1].$store(buffer.offset(16));
                 buffer = buffer.offset(32);
             }
             remaining -= 32;
         }
         while remaining > 16 {
-            compute_rounds!(0, c, r, s, self.round_keys);
-            c[0] = c[0] + one;
+            $compute_rounds!(0, $c, $r, $s, $round_keys);
+            $c[0] = ($add)($c[0], one);
             unsafe {
-                r[0].store(buffer);
+                $r[0].$store(buffer);
                 buffer = buffer.offset(16);
             }
             remaining -= 16;
         }
         if remaining > 0 {
-            compute_rounds!(0, c, r, s, self.round_keys);
-            c[0] = c[0] + one;
+            $compute_rounds!(0, $c, $r, $s, $round_keys);
+            $c[0] = ($add)($c[0], one);
+            let t = $r[0].bytes();
             unsafe {
-                #[repr(align(16))]
-                struct Aligned([u8; 16]);
-                let mut t = MaybeUninit::<Aligned>::uninit();
-                r[0].store(t.as_mut_ptr() as *mut u8);
-                for i in 0..remaining {
-                    buffer.add(i).write((t.as_ptr() as *const u8).add(i).read());
-                }
+                ::core::ptr::copy_nonoverlapping(t.as_ptr(), buffer, remaining);
             }
         }
-        self.counter = c[0];
+        $counter = $c[0];
+    });
+}
 
-        c[0] = c[0] ^ M128::from((1 << 63, 0));
-        compute_rounds!(0, c, r, s, self.round_keys);
-        aes_key_expand_128(&mut self.round_keys, r[0]);
-    }
+/// Log a key erasure after filling `len` bytes, with the `tracing` feature.
+#[inline]
+fn trace_key_erasure(len: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "aesrng::key_erasure", "key erased after {} bytes", len);
+    #[cfg(not(feature = "tracing"))]
+    let _ = len;
 }
 
-impl SeedableRng for AesCore {
-    type Seed = [u8; SEEDBYTES];
+/// Panic if `buffer` cannot be filled using aligned stores only.
+fn assert_aligned(buffer: &[u8]) {
+    assert_eq!(buffer.as_ptr() as usize % 16, 0, "buffer is not 16-byte aligned");
+    assert_eq!(buffer.len() % 16, 0, "buffer length is not a multiple of 16");
+}
 
-    /// Create a new `AesCore` using the given seed.
-    fn from_seed(seed: [u8; SEEDBYTES]) -> AesCore {
+#[cfg(feature = "aead")]
+pub mod aead;
+mod aes256;
+#[cfg(feature = "alloc")]
+mod aligned;
+#[cfg(feature = "chacha")]
+mod any;
+mod counter128;
+mod ctr;
+mod iter;
+#[cfg(feature = "memlock")]
+mod memlock;
+#[cfg(feature = "ocb")]
+pub mod ocb;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod range;
+#[cfg(test)]
+mod reference;
+#[cfg(feature = "serde1")]
+mod serialize;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(all(feature = "std", feature = "getrandom"))]
+mod thread_local;
+#[cfg(all(feature = "vaes", any(target_arch = "x86_64", target_arch = "x86")))]
+mod vaes;
+mod xts;
+
+pub use aes256::{AesCore256, AesRng256, Seed384};
+#[cfg(feature = "alloc")]
+pub use aligned::AlignedBytes;
+#[cfg(feature = "chacha")]
+pub use any::AnyRng;
+pub use counter128::AesCore128BitCounter;
+pub use ctr::AesCtr;
+pub use xts::AesXts;
+pub use iter::{AesRngIter, AesRngIterU32, BlockIter, Limit};
+#[cfg(feature = "memlock")]
+pub use memlock::LockedBox;
+#[cfg(feature = "serde1")]
+pub use serialize::VersionMismatch;
+#[cfg(feature = "rayon")]
+pub use parallel::parallel_fill;
+pub use range::{gen_range, UniformInt};
+#[cfg(feature = "std")]
+pub use shared::SharedAesRng;
+#[cfg(all(feature = "std", feature = "getrandom"))]
+pub use thread_local::thread_local_rng;
+
+/// A fast-key-erasure random-number generator using AES-NI.
+///
+/// This is designed to fill large buffers quickly with random data.
+///
+/// The round keys and counter are plain SIMD values without any pointers or
+/// handles, so `AesCore` is automatically `Send` and `Sync`.
+#[repr(align(16))]
+#[derive(Clone)]
+pub struct AesCore {
+    round_keys: RoundKeys,
+    counter: M128,
+}
+
+// Check the layout at compile time, so changes to the struct cannot silently
+// introduce padding or break the alignment required by the SIMD loads. The
+// array length underflows if a condition is false (`assert!` in constants
+// requires Rust 1.57).
+const _: [(); 0 - !(::core::mem::size_of::<AesCore>() == (ROUNDS + 1) * 16 + 16) as usize] = [];
+const _: [(); 0 - !(::core::mem::align_of::<AesCore>() == 16) as usize] = [];
+
+impl AesCore {
+    /// The period of the keystream for a single key, in bits.
+    ///
+    /// Only the lower 64 bits of the counter are incremented, so a key could
+    /// generate at most 2^64 blocks before the keystream repeats. Since the
+    /// key is erased after every `fill`, this limits the size of a single
+    /// buffer, not the total output.
+    pub const PERIOD_BITS: u32 = 64;
+
+    /// The size of a block of output, in bytes.
+    pub const OUTPUT_BLOCK_BYTES: usize = 16;
+
+    /// Create a new `AesCore` using the given key and initial counter.
+    ///
+    /// Panics if the AES instructions are not available.
+    #[must_use]
+    pub fn new_keyed(key: &[u8; 16], counter: u128) -> AesCore {
+        assert!(is_aes_available(), "AES instructions are not available");
         let zero = M128::from((0, 0));
         let mut round_keys: RoundKeys = [zero; ROUNDS + 1];
-        let key = unsafe { M128::load(seed.as_ptr()) };
-        let counter = unsafe { M128::load(seed.as_ptr().offset(16)) };
+        let key = Block(*key).to_m128i();
+        let counter = Block(counter.to_le_bytes()).to_m128i();
 
-        aes_key_expand_128(&mut round_keys, key);
+        unsafe { aes_key_expand_128(&mut round_keys, key) };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(target: "aesrng::key_erasure", "initial key expanded");
 
         AesCore {
             round_keys,
             counter,
         }
     }
-}
 
-// Custom Debug implementation that does not expose the internal state
-impl fmt::Debug for AesCore {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "AesCore {{}}")
+    /// Create a new `AesCore` using the given seed, rejecting the all-zero
+    /// seed.
+    ///
+    /// Panics if the AES instructions are not available.
+    ///
+    /// An all-zero seed is valid, but it is almost certainly the result of a
+    /// bug, like forgetting to fill the seed buffer. It is not forbidden
+    /// outright, because fixed seeds are legitimately used for reproducible
+    /// tests, and `from_seed` has to accept any seed. Use `from_seed` to skip
+    /// the check.
+    pub fn try_from_seed(seed: [u8; SEEDBYTES]) -> Result<AesCore, WeakSeedError> {
+        if seed.iter().all(|&x| x == 0) {
+            return Err(WeakSeedError);
+        }
+        Ok(AesCore::from_seed(seed))
+    }
+
+    /// Create a new `AesCore` using a seed written by the given function.
+    ///
+    /// Panics if the AES instructions are not available.
+    ///
+    /// This is meant for entropy sources like hardware security modules,
+    /// which write into a caller-provided buffer and may fail. Errors
+    /// returned by `f` are passed through.
+    pub fn seed_from_fn<E, F>(f: F) -> Result<AesCore, E>
+    where
+        F: FnOnce(&mut [u8; SEEDBYTES]) -> Result<(), E>,
+    {
+        let mut seed = [0; SEEDBYTES];
+        f(&mut seed)?;
+        Ok(AesCore::from_seed(seed))
     }
-}
 
-impl<T> AsByteSliceMut for [T; AESRNG_BUFSIZE] where [T]: AsByteSliceMut {
+    /// Fill the given buffer with random data.
+    ///
+    /// Erases the key after filling the buffer. With the `vaes` feature, large
+    /// buffers are filled two blocks at a time on x86 CPUs supporting VAES,
+    /// with the same output.
     #[inline]
-    fn as_byte_slice_mut<'a>(&'a mut self) -> &'a mut [u8] {
-        self[..].as_byte_slice_mut()
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        #[cfg(all(feature = "vaes", any(target_arch = "x86_64", target_arch = "x86")))]
+        {
+            if buffer.len() >= 128 && vaes::is_vaes_available() {
+                unsafe { self.fill_vaes_impl(buffer) };
+                trace_key_erasure(buffer.len());
+                return;
+            }
+        }
+        // An `AesCore` can only be created if the AES instructions are
+        // available.
+        unsafe { self.fill_impl(buffer) };
+        trace_key_erasure(buffer.len());
+    }
+
+    /// Create a new `AesCore` from a passphrase, using PBKDF2-HMAC-SHA256 to
+    /// derive the seed.
+    ///
+    /// The salt should be unique, for example random and stored with the
+    /// derived data. The number of iterations determines the cost of guessing
+    /// the passphrase; use at least 100,000 for interactive use, and more if
+    /// the delay is acceptable. The seed is only as strong as the passphrase,
+    /// so this is not a substitute for proper key management. This is only
+    /// available with the `passphrase` feature.
+    ///
+    /// Panics if the AES instructions are not available.
+    #[cfg(feature = "passphrase")]
+    #[must_use]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], iterations: u32) -> AesCore {
+        let mut seed = [0; SEEDBYTES];
+        ::pbkdf2::pbkdf2_hmac::<::sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut seed);
+        AesCore::from_seed(seed)
+    }
+
+    /// Return a vector of `len` random bytes.
+    ///
+    /// Erases the key after filling the vector. Use `into_boxed_slice` to get
+    /// a `Box<[u8]>`.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn fill_vec(&mut self, len: usize) -> Vec<u8> {
+        let mut buffer = vec![0; len];
+        self.fill(&mut buffer);
+        buffer
+    }
+
+    /// Fill the given buffer with random non-zero bytes.
+    ///
+    /// Each byte is uniformly distributed over 1 to 255. After filling the
+    /// buffer, zero bytes are replaced using rejection sampling: replacement
+    /// bytes are generated 16 at a time with additional calls to `fill`, and
+    /// zero replacements are skipped. About 1/256 of the bytes are zero, so
+    /// the overhead is small for large buffers, but each batch of
+    /// replacements erases the key again. The output differs from `fill`.
+    pub fn fill_nonnull_bytes(&mut self, out: &mut [u8]) {
+        self.fill(out);
+        let mut spare = [0; 16];
+        let mut next = spare.len();
+        for byte in out.iter_mut().filter(|b| **b == 0) {
+            while *byte == 0 {
+                if next == spare.len() {
+                    self.fill(&mut spare);
+                    next = 0;
+                }
+                *byte = spare[next];
+                next += 1;
+            }
+        }
+    }
+
+    /// Return an array of `N` random bytes.
+    ///
+    /// The output is the same as for `fill`. The array is zero-initialized
+    /// first, which the compiler can usually elide. Erases the key after
+    /// filling the array.
+    #[must_use]
+    pub fn fill_into_array<const N: usize>(&mut self) -> [u8; N] {
+        let mut array = [0; N];
+        self.fill(&mut array);
+        array
+    }
+
+    /// Discard `discard_blocks` blocks of output.
+    ///
+    /// Each block is generated by a separate `fill`, so the key is erased
+    /// `discard_blocks` times. Note that the output of AES has no bias that
+    /// would be reduced by discarding initial output. This is only meant for
+    /// protocols that require a warm-up.
+    pub fn warmup(&mut self, discard_blocks: usize) {
+        let mut block = [0; 16];
+        for _ in 0..discard_blocks {
+            self.fill(&mut block);
+        }
+    }
+
+    /// Return an infinite iterator over random 16-byte blocks.
+    ///
+    /// Each block is generated by a separate `fill`, so the key is erased
+    /// after every block. This is convenient for consuming one block at a
+    /// time, but much slower than filling a large buffer.
+    pub fn block_iter(&mut self) -> BlockIter<'_> {
+        BlockIter(self, [0; 16])
+    }
+
+    /// Write `bytes` random bytes to `writer`.
+    ///
+    /// The bytes are generated in chunks of 4 KiB using a buffer on the stack,
+    /// so the output does not have to fit into memory. The key is erased after
+    /// each chunk, so the output differs from a single `fill` of the same
+    /// length.
+    #[cfg(feature = "std")]
+    pub fn fill_into_writer<W: io::Write + ?Sized>(&mut self, writer: &mut W, bytes: usize) -> io::Result<()> {
+        let mut buffer = [0; 4096];
+        let mut remaining = bytes;
+        while remaining > 0 {
+            let n = remaining.min(buffer.len());
+            self.fill(&mut buffer[..n]);
+            writer.write_all(&buffer[..n])?;
+            remaining -= n;
+        }
+        Ok(())
+    }
+
+    /// Return `len` random bytes in a buffer aligned to 64 bytes.
+    ///
+    /// With this alignment, the stores never cross cache lines, which can be
+    /// faster on some CPUs. The output is the same as for `fill`. Erases the
+    /// key after filling the buffer.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn fill_bytes_aligned(&mut self, len: usize) -> AlignedBytes {
+        let mut buffer = AlignedBytes::zeroed(len);
+        self.fill(&mut buffer);
+        buffer
     }
 
+    /// Fill the given slice with random `u32` values.
+    ///
+    /// The values are the same as when interpreting the output of `fill` as
+    /// little-endian integers. Erases the key after filling the buffer.
     #[inline]
-    fn to_le(&mut self) {
-        self[..].to_le()
+    pub fn fill_u32(&mut self, out: &mut [u32]) {
+        self.fill(out.as_byte_slice_mut());
+        out.to_le();
     }
-}
 
-impl BlockRngCore for AesCore {
-    type Item = u32;
-    type Results = [u32; AESRNG_BUFSIZE];
+    /// Fill the given slice with random `u64` values.
+    ///
+    /// The values are the same as when interpreting the output of `fill` as
+    /// little-endian integers. Erases the key after filling the buffer.
+    #[inline]
+    pub fn fill_u64(&mut self, out: &mut [u64]) {
+        self.fill(out.as_byte_slice_mut());
+        out.to_le();
+    }
 
+    /// Fill the given 16-byte aligned buffer with random data.
+    ///
+    /// This uses aligned stores and avoids copying the tail of the buffer, so
+    /// it can be faster than `fill` for large buffers. The output is the same
+    /// as for `fill`. Erases the key after filling the buffer.
+    ///
+    /// Panics if the buffer is not 16-byte aligned or if its length is not a
+    /// multiple of 16.
     #[inline]
-    fn generate(&mut self, results: &mut Self::Results) {
-        self.fill(results.as_byte_slice_mut())
+    pub fn fill_aligned(&mut self, buffer: &mut [u8]) {
+        assert_aligned(buffer);
+        unsafe { self.fill_aligned_impl(buffer) };
+        trace_key_erasure(buffer.len());
     }
-}
 
+    /// Fill the given buffer with random data, without erasing the key.
+    ///
+    /// This skips the key erasure done by `fill`, which is faster when
+    /// filling many small buffers. The output is the same as for `fill`
+    /// without the key erasure, i.e. the counter just continues.
+    ///
+    /// **Do not use this if forward secrecy is required!** An attacker who
+    /// learns the state of the RNG can reconstruct all output generated since
+    /// the key was last erased. This is meant for non-cryptographic bulk data,
+    /// like test fixtures or simulations.
+    #[inline]
+    pub fn fill_fast(&mut self, buffer: &mut [u8]) {
+        unsafe { self.fill_fast_impl(buffer) }
+    }
 
-/// A fast-key-erasure random-number generator using AES-NI.
-///
-/// Internally, it uses a 128 byte buffer for generating integers. When the
-/// buffer is consumed, the key of the RNG is erased.
-///
-/// Using `fill`, `fill_bytes` or `try_fill_bytes` always erases the key after
-/// filling the buffer. This implies that `next_u32` and `next_u64` will not
-/// generate the same results.
-#[derive(Clone, Debug)]
-pub struct AesRng(BlockRng<AesCore>);
+    /// Fill the given buffer with random data, keeping the intermediate state
+    /// in `scratch` instead of on the stack.
+    ///
+    /// `fill` keeps 384 bytes of counters and intermediate state on the stack,
+    /// which may be too much in stack-constrained contexts like kernels or
+    /// embedded systems. The output is the same as for `fill`. Erases the key
+    /// after filling the buffer.
+    ///
+    /// Afterwards, `scratch` contains intermediate state of the RNG. It should
+    /// be treated as sensitive and cleared using `FillScratch::clear` once it
+    /// is no longer needed.
+    #[inline]
+    pub fn fill_with_scratch(&mut self, buffer: &mut [u8], scratch: &mut FillScratch) {
+        unsafe { self.fill_with_scratch_impl(buffer, scratch) };
+        trace_key_erasure(buffer.len());
+    }
 
-impl RngCore for AesRng {
-    #[inline(always)]
-    fn next_u32(&mut self) -> u32 {
-        self.0.next_u32()
+    /// Fill the given buffer with random data, erasing the key when the
+    /// counter wraps around.
+    ///
+    /// If the lower 64 bits of the counter wrap around to zero inside the
+    /// buffer, the key is erased at that point and filling continues with the
+    /// new key from the wrapped counter, so the blocks after the wrap-around
+    /// never use a counter value with the key used before it. Otherwise, this
+    /// is the same as `fill`. Erases the key after filling the buffer.
+    pub fn fill_saturating(&mut self, buffer: &mut [u8]) {
+        let low = self.get_counter() as u64;
+        // The number of bytes until the wrap-around, at most 2^68.
+        let until_wrap = (u128::from(u64::MAX - low) + 1) * 16;
+        if buffer.len() as u128 > until_wrap {
+            let (head, tail) = buffer.split_at_mut(until_wrap as usize);
+            self.fill(head);
+            self.fill(tail);
+        } else {
+            self.fill(buffer);
+        }
     }
 
-    #[inline(always)]
-    fn next_u64(&mut self) -> u64 {
-        self.0.next_u64()
+    /// Fill 128 bytes, computing the rounds of all 8 blocks in lockstep.
+    ///
+    /// Unlike `fill`, which computes the blocks one after the other, this
+    /// applies each round to all 8 blocks before the next round, keeping the
+    /// state in registers until all blocks are stored. This exists for
+    /// benchmarking the pipelining of the AES instructions (see the
+    /// `turbo_fill` benchmark). The output is the same as for `fill`. Erases
+    /// the key after filling the buffer.
+    #[doc(hidden)]
+    pub fn turbo_fill_128(&mut self, out: &mut [u8; 128]) {
+        // An `AesCore` can only be created if the AES instructions are
+        // available.
+        unsafe { self.turbo_fill_128_impl(out) };
+        trace_key_erasure(out.len());
     }
 
-    fn fill_bytes(&mut self, dest: &mut [u8]) {
-        self.0.core.fill(dest)
+    /// Like `fill`, but computes `UNROLL` blocks per loop iteration.
+    ///
+    /// This only exists for benchmarking the loop structure of `fill`. The
+    /// output is the same as for `fill`.
+    ///
+    /// Panics if `UNROLL` is zero.
+    #[doc(hidden)]
+    pub fn fill_unrolled<const UNROLL: usize>(&mut self, buffer: &mut [u8]) {
+        assert!(UNROLL > 0);
+        unsafe { self.fill_unrolled_impl::<UNROLL>(buffer) }
     }
 
-    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
-        Ok(self.0.core.fill(dest))
+    /// Return the current value of the counter.
+    ///
+    /// The counter is the input of the block cipher for the next block of
+    /// output. Only the lower 64 bits are incremented for each block, they
+    /// wrap around without carrying into the upper 64 bits.
+    ///
+    /// A single `fill` never uses a counter value twice, because a buffer
+    /// holds less than 2^60 blocks. Across calls, only the key erasure
+    /// prevents repeating the keystream: clones and `AesCore`s created with
+    /// the same key repeat it for the same counter values, and `fill_fast`
+    /// does not erase the key, so after `set_counter` or the wrap-around it
+    /// can revisit counter values. See `fill_saturating` for erasing the key
+    /// at the wrap-around.
+    #[inline]
+    #[must_use]
+    pub fn get_counter(&self) -> u128 {
+        u128::from_le_bytes(self.counter.bytes())
     }
-}
 
-impl SeedableRng for AesRng {
-    type Seed = <AesCore as SeedableRng>::Seed;
+    /// Set the counter, jumping to an arbitrary position in the stream.
+    ///
+    /// This keeps the current round keys, it does not re-expand the key. This
+    /// allows using the keystream as a PRF over several messages.
+    ///
+    /// Note that the output also depends on the key, which is erased by every
+    /// call to `fill`. Setting the counter to an earlier value after `fill`
+    /// does not reproduce the previous output, but it does with `fill_fast`
+    /// or on a clone made before the `fill`.
+    #[inline]
+    pub fn set_counter(&mut self, position: u128) {
+        self.counter = M128::from(((position >> 64) as i64, position as i64));
+    }
 
-    fn from_seed(seed: Self::Seed) -> Self {
-        AesRng(BlockRng::<AesCore>::from_seed(seed))
+    /// Skip `blocks` blocks of output, keeping the current round keys.
+    ///
+    /// Like for `fill`, only the lower 64 bits of the counter are advanced,
+    /// wrapping around without carrying into the upper 64 bits.
+    #[inline]
+    pub fn advance_counter(&mut self, blocks: u64) {
+        self.counter = self.counter + M128::from((0, blocks as i64));
     }
 
-    fn from_rng<R: RngCore>(rng: R) -> Result<Self, Error> {
-        BlockRng::<AesCore>::from_rng(rng).map(|rng| AesRng(rng))
+    /// Replace the key, keeping the current counter.
+    ///
+    /// This is meant for custom protocols, for example switching to a session
+    /// key from a key agreement. The old round keys are overwritten, but this
+    /// does not provide forward secrecy for output generated with the old key
+    /// if copies of it remain elsewhere, like in clones of this `AesCore` or
+    /// in moved-from memory. Use the `zeroize` feature to erase those.
+    pub fn set_key(&mut self, key: &[u8; 16]) {
+        // An `AesCore` can only be created if the AES instructions are
+        // available.
+        unsafe { aes_key_expand_128(&mut self.round_keys, Block(*key).to_m128i()) };
     }
-}
 
-impl CryptoRng for AesRng {}
+    /// Mix external entropy into the state and derive a new key and counter.
+    ///
+    /// Each 16 byte chunk of `additional` is XOR-ed into the counter, followed
+    /// by generating a new seed using `fill`, which erases the previous key.
+    /// This is not a standards-compliant DRBG, but it provides forward secrecy
+    /// after each reseed, and the state is unpredictable if `additional` is.
+    pub fn reseed(&mut self, additional: &[u8]) {
+        for chunk in additional.chunks(16) {
+            self.absorb(chunk);
+        }
+        if additional.is_empty() {
+            self.absorb(&[]);
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    extern crate itertools;
+    /// Replace the key and counter by a fresh seed from the operating system's
+    /// random-number generator.
+    ///
+    /// The state is overwritten in place. Unlike `reseed`, the new state does
+    /// not depend on the previous one.
+    #[cfg(feature = "getrandom")]
+    pub fn reseed_from_os(&mut self) -> Result<(), getrandom::Error> {
+        let mut seed = [0; SEEDBYTES];
+        getrandom::getrandom(&mut seed)?;
+        *self = AesCore::from_seed(seed);
+        Ok(())
+    }
 
-    use self::itertools::Itertools;
+    /// Derive key material bound to `label`, filling `output`.
+    ///
+    /// The label length and then the label are absorbed 16 bytes at a time,
+    /// like for `reseed`, and `output` is filled using the resulting state.
+    /// Each block replaces the key by one derived from the previous state, so
+    /// distinct labels give unrelated outputs. This leaves `self` in the state
+    /// after filling `output`, so deriving with the same label twice gives
+    /// different results.
+    ///
+    /// This is a simple, non-standard KDF meant for deriving subkeys
+    /// internally. Use a standard KDF like HKDF if interoperability is
+    /// required.
+    pub fn derive_key(&mut self, label: &[u8], output: &mut [u8]) {
+        // The length prefix makes the zero padding of the last chunk
+        // unambiguous.
+        self.absorb(&(label.len() as u64).to_le_bytes());
+        for chunk in label.chunks(16) {
+            self.absorb(chunk);
+        }
+        self.fill(output);
+    }
 
-    use super::*;
+    fn absorb(&mut self, chunk: &[u8]) {
+        let mut block = Block::default();
+        block.0[..chunk.len()].copy_from_slice(chunk);
+        self.counter = self.counter ^ block.to_m128i();
+        let mut seed = [0; SEEDBYTES];
+        self.fill(&mut seed);
+        *self = AesCore::from_seed(seed);
+    }
 
-    #[test]
-    fn size() {
-        assert_eq!(std::mem::size_of::<AesCore>(), (ROUNDS + 1) * 16 + 16);
+    /// Derive an independent `AesCore` for the given stream ID.
+    ///
+    /// The new key is computed by encrypting a block consisting of
+    /// `stream_id` in the lower 64 bits and the upper 64 bits of the counter
+    /// with bit 62 flipped, and the new counter starts at zero. The output
+    /// blocks only increment the lower 64 bits, and the key erasure flips
+    /// bit 63, so the child key is never equal to an output block of `self`
+    /// (unless the counter is changed using `set_counter`). `AesCore`s forked
+    /// with different stream IDs produce unrelated streams. This does not
+    /// modify `self`, forking twice with the same stream ID yields the same
+    /// stream.
+    #[must_use]
+    pub fn fork_stream(&self, stream_id: u64) -> AesCore {
+        unsafe { self.fork_stream_impl(stream_id) }
     }
 
-    #[test]
-    fn from_seed() {
-        let rng = AesCore::from_seed([
-            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
-            11, 12, 13, 14, 15,
-        ]);
-        {
-            let mut hex = String::new();
-            for &key in rng.round_keys.iter() {
-                let buf = key.bytes();
-                hex.push_str(&format!("{:02x}", buf.iter().format("")));
-            }
-            let expected = "000102030405060708090a0b0c0d0e0fd6aa74fdd2af72fadaa678f1d6ab76feb692cf0b643dbdf1be9bc5006830b3feb6ff744ed2c2c9bf6c590cbf0469bf4147f7f7bc95353e03f96c32bcfd058dfd3caaa3e8a99f9deb50f3af57adf622aa5e390f7df7a69296a7553dc10aa31f6b14f9701ae35fe28c440adf4d4ea9c02647438735a41c65b9e016baf4aebf7ad2549932d1f08557681093ed9cbe2c974e13111d7fe3944a17f307a78b4d2b30c5";
-            assert_eq!(hex, expected);
-        }
-        {
-            let buf = rng.counter.bytes();
-            let hex = format!("{:02x}", buf.iter().format(""));
-            let expected = "000102030405060708090a0b0c0d0e0f";
-            assert_eq!(hex, expected);
+    /// Split off a child `AesCore` with an independent key and counter.
+    ///
+    /// The child is seeded from 32 bytes of output, so this advances `self`
+    /// and erases its key, like `fill`.
+    #[must_use]
+    pub fn split(&mut self) -> AesCore {
+        let mut seed = [0; SEEDBYTES];
+        self.fill(&mut seed);
+        AesCore::from_seed(seed)
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fork_stream_impl(&self, stream_id: u64) -> AesCore {
+        let zero = M128::from((0, 0));
+        let mut high = [0; 8];
+        high.copy_from_slice(&self.counter.bytes()[8..]);
+        let high = u64::from_le_bytes(high) ^ (1 << 62);
+        let c = [M128::from((high as i64, stream_id as i64))];
+        let mut r = [zero];
+        let mut s = [zero];
+        compute_rounds!(0, c, r, s, self.round_keys);
+
+        let mut round_keys: RoundKeys = [zero; ROUNDS + 1];
+        aes_key_expand_128(&mut round_keys, r[0]);
+        AesCore {
+            round_keys,
+            counter: zero,
         }
     }
 
-    #[test]
-    fn fill() {
-        let mut rng = AesCore::from_seed([
-            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
-            11, 12, 13, 14, 15,
-        ]);
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_impl(&mut self, buffer: &mut [u8]) {
+        fill!(compute_rounds, store, self.round_keys, self.counter, buffer);
+        self.erase_key();
+    }
 
-        let mut buf = vec![0; 200];
-        rng.fill(&mut buf);
-        let hex = format!("{:02x}", buf.iter().format(""));
-        let expected = "ddc1766018f72b77a8218c6593de2788f2d1e380d80f0c4d0fc2c294167b8f54a891572bf85fa4c4577a0af946d8a7c0c0b7c4efc6c580ded5616d6c99e2012f37f3c0ccc8815a805fc312cc59ecf9bb77723f91877423bed3f5c2204b17f0cd440543c647c4d1c55b7a5700041484ed3680785e09f51a77845578d51c7276cc19de1941f33ad0112665e9771aba4e07a204537666a96d6f9089497ca50810f5007940a574ef767e6aa7dc1b657bea655e6969c424c173fa346fb6f88412db459c6c0f6fc4c8de91";
-        assert_eq!(hex, expected);
+    /// Encrypt a single block using plain AES128 (ECB mode) with the current
+    /// round keys.
+    ///
+    /// This allows building other modes on top of the key schedule, for
+    /// example using an `AesCore` created by `new_keyed`. Unlike `fill`, this
+    /// does not feed forward the state and does not erase the key. Note that
+    /// the key changes after each `fill`.
+    #[must_use]
+    pub fn encrypt_block(&self, plaintext: &[u8; 16]) -> [u8; 16] {
+        // An `AesCore` can only be created if the AES instructions are
+        // available.
+        unsafe { encrypt_block(&self.round_keys, Block(*plaintext).to_m128i()).bytes() }
+    }
+
+    /// Decrypt a single block using plain AES128 (ECB mode) with the current
+    /// round keys, inverting `encrypt_block`.
+    ///
+    /// The round keys for the equivalent inverse cipher are derived on each
+    /// call, which costs about as much as decrypting a block. They are not
+    /// cached, because the key changes after each `fill`.
+    #[must_use]
+    pub fn decrypt_block(&self, ciphertext: &[u8; 16]) -> [u8; 16] {
+        // An `AesCore` can only be created if the AES instructions are
+        // available.
+        unsafe { self.decrypt_block_impl(Block(*ciphertext).to_m128i()).bytes() }
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn decrypt_block_impl(&self, block: M128) -> M128 {
+        let mut r = block ^ self.round_keys[ROUNDS];
+        for &key in self.round_keys[1..ROUNDS].iter().rev() {
+            r = r.decrypt(key.inv_mix_columns());
+        }
+        r.decrypt_last(self.round_keys[0])
+    }
+
+    /// Fill 4 blocks, like `fill` but without the loops for other lengths.
+    #[inline]
+    fn fill_4_blocks(&mut self, buffer: &mut [u8; 64]) {
+        unsafe { self.fill_4_blocks_impl(buffer) };
+        trace_key_erasure(buffer.len());
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_4_blocks_impl(&mut self, buffer: &mut [u8; 64]) {
+        let zero = M128::from((0, 0));
+        let one = M128::from((0, 1));
+        let mut r = [zero; 4];
+        let mut s = [zero; 4];
+        let c = [
+            self.counter,
+            self.counter + one,
+            self.counter + M128::from((0, 2)),
+            self.counter + M128::from((0, 3)),
+        ];
+        compute_rounds!(0, c, r, s, self.round_keys);
+        compute_rounds!(1, c, r, s, self.round_keys);
+        compute_rounds!(2, c, r, s, self.round_keys);
+        compute_rounds!(3, c, r, s, self.round_keys);
+        self.counter = c[3] + one;
+        for (i, block) in r.iter().enumerate() {
+            block.store(buffer.as_mut_ptr().add(16 * i));
+        }
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn turbo_fill_128_impl(&mut self, out: &mut [u8; 128]) {
+        let zero = M128::from((0, 0));
+        let one = M128::from((0, 1));
+        let mut r = [zero; 8];
+        let mut c = self.counter;
+        for block in r.iter_mut() {
+            *block = c ^ self.round_keys[0];
+            c = c + one;
+        }
+        self.counter = c;
+        for &key in &self.round_keys[1..6] {
+            for block in r.iter_mut() {
+                *block = block.encrypt(key);
+            }
+        }
+        let s = r;
+        for &key in &self.round_keys[6..ROUNDS] {
+            for block in r.iter_mut() {
+                *block = block.encrypt(key);
+            }
+        }
+        for (i, block) in r.iter().enumerate() {
+            let block = s[i] ^ block.encrypt_last(self.round_keys[ROUNDS]);
+            block.store(out.as_mut_ptr().add(16 * i));
+        }
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_fast_impl(&mut self, buffer: &mut [u8]) {
+        fill!(compute_rounds, store, self.round_keys, self.counter, buffer);
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_with_scratch_impl(&mut self, buffer: &mut [u8], scratch: &mut FillScratch) {
+        let FillScratch { c, r, s } = scratch;
+        fill!(@scratch c, r, s, compute_rounds, store, ::core::ops::Add::add, self.round_keys, self.counter, buffer);
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_aligned_impl(&mut self, buffer: &mut [u8]) {
+        fill!(compute_rounds, store_aligned, self.round_keys, self.counter, buffer);
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn fill_unrolled_impl<const UNROLL: usize>(&mut self, buffer: &mut [u8]) {
+        let zero = M128::from((0, 0));
+        let one = M128::from((0, 1));
+        let mut c = [zero; UNROLL];
+        let mut r = [zero; UNROLL];
+        let mut s = [zero; UNROLL];
+
+        let mut chunks = buffer.chunks_exact_mut(16 * UNROLL);
+        for chunk in &mut chunks {
+            c[0] = self.counter;
+            for i in 1..UNROLL {
+                c[i] = c[i - 1] + one;
+            }
+            for i in 0..UNROLL {
+                compute_rounds!(i, c, r, s, self.round_keys);
+            }
+            self.counter = c[UNROLL - 1] + one;
+            for (i, block) in r.iter().enumerate() {
+                block.store(chunk.as_mut_ptr().add(16 * i));
+            }
+        }
+        for chunk in chunks.into_remainder().chunks_mut(16) {
+            c[0] = self.counter;
+            compute_rounds!(0, c, r, s, self.round_keys);
+            self.counter = self.counter + one;
+            chunk.copy_from_slice(&r[0].bytes()[..chunk.len()]);
+        }
+        self.erase_key();
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn erase_key(&mut self) {
+        let zero = M128::from((0, 0));
+        let c = [self.counter ^ M128::from((1 << 63, 0))];
+        let mut r = [zero];
+        let mut s = [zero];
+        compute_rounds!(0, c, r, s, self.round_keys);
+        aes_key_expand_128(&mut self.round_keys, r[0]);
+    }
+}
+
+impl SeedableRng for AesCore {
+    type Seed = [u8; SEEDBYTES];
+
+    /// Create a new `AesCore` using the given seed.
+    ///
+    /// Panics if the AES instructions are not available.
+    ///
+    /// The first 16 bytes are used as the key, the last 16 bytes as the
+    /// little-endian initial counter, see `AesCore::new_keyed`. The seed is
+    /// not checked, see `AesCore::try_from_seed`.
+    fn from_seed(seed: [u8; SEEDBYTES]) -> AesCore {
+        let mut key = [0; 16];
+        let mut counter = [0; 16];
+        key.copy_from_slice(&seed[..16]);
+        counter.copy_from_slice(&seed[16..]);
+        AesCore::new_keyed(&key, u128::from_le_bytes(counter))
+    }
+}
+
+/// Create an `AesCore` using the all-zero seed.
+///
+/// Using a fixed seed is insecure, because the output is predictable. This
+/// is only meant for initializing structs which are seeded later. Prefer
+/// `AesCore::from_seed` with a secret seed or `AesRng::from_entropy`.
+///
+/// Panics if the AES instructions are not available.
+impl Default for AesCore {
+    fn default() -> AesCore {
+        AesCore::from_seed([0; SEEDBYTES])
+    }
+}
+
+/// Fill `buf` with random data derived from `key` and `nonce`, without keeping
+/// any state.
+///
+/// This is equivalent to filling `buf` using
+/// `AesCore::new_keyed(key, (nonce as u128) << 64)`. The nonce is placed in the
+/// upper 64 bits of the counter, which are never incremented, so the outputs
+/// for different nonces do not overlap. The same key and nonce always give
+/// the same output.
+///
+/// Panics if the AES instructions are not available.
+#[inline]
+pub fn fill_with_nonce(key: &[u8; 16], nonce: u64, buf: &mut [u8]) {
+    AesCore::new_keyed(key, u128::from(nonce) << 64).fill(buf);
+}
+
+/// Create an `AesCore` using the given key, with the counter starting at zero.
+///
+/// Use `AesCore::new_keyed` for a different initial counter.
+///
+/// Panics if the AES instructions are not available.
+impl From<[u8; 16]> for AesCore {
+    fn from(key: [u8; 16]) -> AesCore {
+        AesCore::new_keyed(&key, 0)
+    }
+}
+
+/// Caller-provided storage for the intermediate state of
+/// `AesCore::fill_with_scratch`.
+///
+/// After filling, this contains sensitive intermediate state of the RNG, so
+/// it should be cleared using `clear` once it is no longer needed. With the
+/// `zeroize` feature, it is also cleared when dropped.
+#[repr(align(16))]
+#[derive(Clone)]
+pub struct FillScratch {
+    c: [M128; 8],
+    r: [M128; 8],
+    s: [M128; 8],
+}
+
+impl FillScratch {
+    /// Create a new zeroed scratch buffer.
+    #[must_use]
+    pub fn new() -> FillScratch {
+        let zero = M128::from((0, 0));
+        FillScratch {
+            c: [zero; 8],
+            r: [zero; 8],
+            s: [zero; 8],
+        }
+    }
+
+    /// Overwrite the contents with zeros.
+    pub fn clear(&mut self) {
+        erase(&mut self.c);
+        erase(&mut self.r);
+        erase(&mut self.s);
+    }
+}
+
+impl Default for FillScratch {
+    fn default() -> FillScratch {
+        FillScratch::new()
+    }
+}
+
+// Custom Debug implementation that does not expose the internal state
+impl fmt::Debug for FillScratch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FillScratch {{}}")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for FillScratch {
+    fn zeroize(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for FillScratch {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for FillScratch {}
+
+/// Create an `AesCore` from key material of any length, like a shared secret.
+///
+/// The seed is derived using HKDF-SHA256 without salt, with `aesrng seed` as
+/// the info string. This is only available with the `kdf` feature.
+///
+/// Returns an error if the key material is empty. Panics if the AES
+/// instructions are not available.
+#[cfg(feature = "kdf")]
+impl<'a> ::core::convert::TryFrom<&'a [u8]> for AesCore {
+    type Error = EmptyInput;
+
+    fn try_from(key_material: &'a [u8]) -> Result<AesCore, EmptyInput> {
+        if key_material.is_empty() {
+            return Err(EmptyInput);
+        }
+        let mut seed = [0; SEEDBYTES];
+        ::hkdf::Hkdf::<::sha2::Sha256>::new(None, key_material)
+            .expand(b"aesrng seed", &mut seed)
+            .expect("32 bytes is a valid length for HKDF-SHA256");
+        Ok(AesCore::from_seed(seed))
+    }
+}
+
+/// Error returned when creating an `AesCore` from empty key material.
+#[cfg(feature = "kdf")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmptyInput;
+
+#[cfg(feature = "kdf")]
+impl fmt::Display for EmptyInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the key material is empty")
+    }
+}
+
+#[cfg(all(feature = "kdf", feature = "std"))]
+impl ::std::error::Error for EmptyInput {}
+
+/// Error returned by `AesRngN::from_seed_slice` for seeds of the wrong length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthError {
+    /// The required length in bytes.
+    pub expected: usize,
+    /// The length of the given seed in bytes.
+    pub got: usize,
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the seed must be {} bytes long, but got {} bytes", self.expected, self.got)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for LengthError {}
+
+/// Error returned by `AesRngN::from_hex_seed` for invalid hexadecimal seeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexDecodeError {
+    /// Why the seed could not be decoded.
+    pub reason: HexErrorKind,
+}
+
+/// The reason for a `HexDecodeError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexErrorKind {
+    /// The string does not have 64 digits.
+    WrongLength,
+    /// The string contains a character which is not a hexadecimal digit.
+    InvalidCharacter,
+    /// The string has an odd number of digits.
+    OddLength,
+}
+
+impl fmt::Display for HexDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reason {
+            HexErrorKind::WrongLength => write!(f, "the seed must be 64 hexadecimal digits long"),
+            HexErrorKind::InvalidCharacter => write!(f, "the seed contains an invalid character"),
+            HexErrorKind::OddLength => write!(f, "the seed has an odd number of digits"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for HexDecodeError {}
+
+/// Error returned by `AesCore::try_from_seed` for the all-zero seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeakSeedError;
+
+impl fmt::Display for WeakSeedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the seed is all zeros")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for WeakSeedError {}
+
+// Custom Debug implementation that shows the counter, but not the key
+impl fmt::Debug for AesCore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AesCore {{ counter: {:#034x} }}", self.get_counter())
+    }
+}
+
+/// Compares the round keys and the counter.
+///
+/// With the `subtle` feature, the comparison runs in constant time.
+impl PartialEq for AesCore {
+    fn eq(&self, other: &AesCore) -> bool {
+        let blocks = self.round_keys.iter().chain(Some(&self.counter))
+            .zip(other.round_keys.iter().chain(Some(&other.counter)));
+        #[cfg(feature = "subtle")]
+        {
+            use subtle::{Choice, ConstantTimeEq};
+
+            let mut eq = Choice::from(1);
+            for (a, b) in blocks {
+                eq &= a.bytes().ct_eq(&b.bytes());
+            }
+            eq.into()
+        }
+        #[cfg(not(feature = "subtle"))]
+        {
+            blocks.fold(true, |eq, (a, b)| eq & (a.bytes() == b.bytes()))
+        }
+    }
+}
+
+impl Eq for AesCore {}
+
+/// Hashes the bytes of the round keys and the counter, consistent with
+/// `PartialEq`.
+impl Hash for AesCore {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for block in self.round_keys.iter().chain(Some(&self.counter)) {
+            block.bytes().hash(state);
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for AesCore {
+    fn zeroize(&mut self) {
+        erase(&mut self.round_keys);
+        erase(::core::slice::from_mut(&mut self.counter));
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for AesCore {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for AesCore {}
+
+impl<T, const N: usize> AsByteSliceMut for [T; N] where [T]: AsByteSliceMut {
+    #[inline]
+    fn as_byte_slice_mut(&mut self) -> &mut [u8] {
+        self[..].as_byte_slice_mut()
+    }
+
+    #[inline]
+    fn to_le(&mut self) {
+        self[..].to_le()
+    }
+}
+
+impl BlockRngCore for AesCore {
+    type Item = u32;
+    type Results = [u32; AESRNG_BUFSIZE];
+
+    #[inline(always)]
+    fn generate(&mut self, results: &mut Self::Results) {
+        // `fill` writes the output bytes in the same order on every platform.
+        // Reading them back as native integers gives big-endian values on
+        // big-endian platforms, so they are byte-swapped to match the
+        // little-endian interpretation used on all platforms.
+        self.fill(results.as_byte_slice_mut());
+        results.to_le();
+    }
+}
+
+impl CryptoRng for AesCore {}
+
+
+/// Buffer of `N` random `u32` values, see `AesCoreN`.
+#[derive(Clone)]
+pub struct Results<const N: usize>(pub [u32; N]);
+
+impl<const N: usize> Default for Results<N> {
+    fn default() -> Results<N> {
+        Results([0; N])
+    }
+}
+
+impl<const N: usize> AsRef<[u32]> for Results<N> {
+    fn as_ref(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl<const N: usize> AsMut<[u32]> for Results<N> {
+    fn as_mut(&mut self) -> &mut [u32] {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Zeroize for Results<N> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Drop for Results<N> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> ZeroizeOnDrop for Results<N> {}
+
+/// An `AesCore` generating `N` `u32` values at a time.
+///
+/// This is used by `AesRngN` to configure the size of the buffer.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+pub struct AesCoreN<const N: usize>(pub AesCore);
+
+impl<const N: usize> From<AesCore> for AesCoreN<N> {
+    fn from(core: AesCore) -> AesCoreN<N> {
+        AesCoreN(core)
+    }
+}
+
+impl<const N: usize> BlockRngCore for AesCoreN<N> {
+    type Item = u32;
+    type Results = Results<N>;
+
+    #[inline(always)]
+    fn generate(&mut self, results: &mut Results<N>) {
+        let bytes = results.0.as_byte_slice_mut();
+        match <&mut [u8; 64] as ::core::convert::TryFrom<_>>::try_from(bytes) {
+            Ok(bytes) => self.0.fill_4_blocks(bytes),
+            Err(_) => self.0.fill(results.0.as_byte_slice_mut()),
+        }
+        results.0.to_le();
+    }
+}
+
+impl<const N: usize> SeedableRng for AesCoreN<N> {
+    type Seed = <AesCore as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> AesCoreN<N> {
+        AesCoreN(AesCore::from_seed(seed))
+    }
+}
+
+impl<const N: usize> CryptoRng for AesCoreN<N> {}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Zeroize for AesCoreN<N> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> ZeroizeOnDrop for AesCoreN<N> {}
+
+/// A fast-key-erasure random-number generator using AES-NI, with a buffer of
+/// `N` `u32` values.
+///
+/// Internally, it uses a `4 * N` byte buffer for generating integers. When the
+/// buffer is consumed, the key of the RNG is erased. A larger buffer amortizes
+/// the cost of the key erasure, a smaller one reduces the memory usage.
+///
+/// Using `fill`, `fill_bytes` or `try_fill_bytes` always erases the key after
+/// filling the buffer. This implies that `next_u32` and `next_u64` will not
+/// generate the same results.
+///
+/// `fill_bytes` and `try_fill_bytes` bypass the buffer, any buffered values
+/// are still returned by the following calls to `next_u32` and `next_u64`.
+/// Because the buffer was generated before the key was erased, mixing these
+/// methods (for example when sampling from distributions) does not repeat any
+/// output.
+///
+/// Cloning copies the buffered values and the position in the buffer, so the
+/// clone produces exactly the same output as the original.
+///
+/// The RNG is `Send` and `Sync`, because it only consists of plain values.
+/// Generating numbers requires `&mut self`, so it cannot be shared between
+/// threads without synchronization. Use `SharedAesRng` or one RNG per thread
+/// instead.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+pub struct AesRngN<const N: usize>(BlockRng<AesCoreN<N>>);
+
+/// A fast-key-erasure random-number generator using AES-NI.
+///
+/// This is an `AesRngN` using a 128 byte buffer.
+pub type AesRng = AesRngN<AESRNG_BUFSIZE>;
+
+/// An `AesCore` generating 64 bytes at a time, see `AesRng64`.
+pub type AesCore64 = AesCoreN<16>;
+
+/// A fast-key-erasure random-number generator using AES-NI, with a 64 byte
+/// buffer.
+///
+/// The buffer is refilled using a loop computing 4 blocks at once. This
+/// reduces the latency of the calls to `next_u32` and `next_u64` that refill
+/// the buffer, at the cost of erasing the key twice as often as `AesRng`.
+pub type AesRng64 = AesRngN<16>;
+
+impl<const N: usize> AesRngN<N> {
+    /// The size of the AES key, in bits.
+    pub const KEY_BITS: u32 = 128;
+
+    /// Create a new RNG using the given seed, if the AES instructions are
+    /// available.
+    #[must_use]
+    pub fn new_if_available(seed: [u8; SEEDBYTES]) -> Option<AesRngN<N>> {
+        if is_aes_available() {
+            Some(AesRngN::from_seed(seed))
+        } else {
+            None
+        }
+    }
+
+    /// Create a new RNG using the seed in the given slice.
+    ///
+    /// Returns an error if the slice is not exactly 32 bytes long. Panics if
+    /// the AES instructions are not available.
+    pub fn from_seed_slice(seed: &[u8]) -> Result<AesRngN<N>, LengthError> {
+        if seed.len() != SEEDBYTES {
+            return Err(LengthError { expected: SEEDBYTES, got: seed.len() });
+        }
+        let mut array = [0; SEEDBYTES];
+        array.copy_from_slice(seed);
+        Ok(AesRngN::from_seed(array))
+    }
+
+    /// Create a new RNG from a `u64`, for reproducible tests.
+    ///
+    /// The seed consists of `s` as 8 little-endian bytes, followed by 24 zero
+    /// bytes. Unlike `SeedableRng::seed_from_u64`, this layout is part of the
+    /// API and will not change. Panics if the AES instructions are not
+    /// available.
+    ///
+    /// The output is predictable, so this is insecure for anything but
+    /// testing.
+    #[must_use]
+    pub fn from_u64_seed(s: u64) -> AesRngN<N> {
+        let mut seed = [0; SEEDBYTES];
+        seed[..8].copy_from_slice(&s.to_le_bytes());
+        AesRngN::from_seed(seed)
+    }
+
+    /// Create a new RNG using a seed given as 64 hexadecimal digits.
+    ///
+    /// Both lower and upper case digits are accepted. Panics if the AES
+    /// instructions are not available.
+    #[allow(clippy::manual_is_multiple_of)]  // `is_multiple_of` requires Rust 1.87.
+    pub fn from_hex_seed(hex: &str) -> Result<AesRngN<N>, HexDecodeError> {
+        let hex = hex.as_bytes();
+        if hex.len() % 2 != 0 {
+            return Err(HexDecodeError { reason: HexErrorKind::OddLength });
+        }
+        if hex.len() != 2 * SEEDBYTES {
+            return Err(HexDecodeError { reason: HexErrorKind::WrongLength });
+        }
+        fn digit(c: u8) -> Result<u8, HexDecodeError> {
+            match c {
+                b'0'..=b'9' => Ok(c - b'0'),
+                b'a'..=b'f' => Ok(c - b'a' + 10),
+                b'A'..=b'F' => Ok(c - b'A' + 10),
+                _ => Err(HexDecodeError { reason: HexErrorKind::InvalidCharacter }),
+            }
+        }
+        let mut seed = [0; SEEDBYTES];
+        for (byte, pair) in seed.iter_mut().zip(hex.chunks(2)) {
+            *byte = digit(pair[0])? << 4 | digit(pair[1])?;
+        }
+        Ok(AesRngN::from_seed(seed))
+    }
+
+    /// Create a new RNG seeded from the operating system's random-number
+    /// generator.
+    ///
+    /// This is the recommended way to create an `AesRng` in security-sensitive
+    /// contexts. Panics if the AES instructions are not available.
+    #[cfg(feature = "getrandom")]
+    pub fn from_entropy() -> Result<AesRngN<N>, getrandom::Error> {
+        let mut seed = [0; SEEDBYTES];
+        getrandom::getrandom(&mut seed)?;
+        Ok(AesRngN::from_seed(seed))
+    }
+}
+
+impl<const N: usize> AesRngN<N> {
+    /// Mix external entropy into the state, see `AesCore::reseed`.
+    ///
+    /// This discards any buffered values.
+    pub fn reseed(&mut self, additional: &[u8]) {
+        self.0.core.0.reseed(additional);
+        self.0.reset();
+    }
+
+    /// Reseed from the operating system's random-number generator, see
+    /// `AesCore::reseed_from_os`.
+    ///
+    /// This discards any buffered values.
+    #[cfg(feature = "getrandom")]
+    pub fn reseed_from_os(&mut self) -> Result<(), getrandom::Error> {
+        self.0.core.0.reseed_from_os()?;
+        self.0.reset();
+        Ok(())
+    }
+
+    /// Return the RNG as a trait object, for functions taking
+    /// `&mut dyn RngCore`.
+    pub fn as_rng_core(&mut self) -> &mut dyn RngCore {
+        self
+    }
+}
+
+impl AesRng {
+    /// Return the `AesRng` of the current thread, see `thread_local_rng`.
+    #[cfg(all(feature = "std", feature = "getrandom"))]
+    #[must_use]
+    pub fn thread_local() -> impl ::core::ops::DerefMut<Target = AesRng> {
+        thread_local_rng()
+    }
+
+    /// Create `n` independent RNGs from one master seed, for example one per
+    /// thread of a parallel simulation.
+    ///
+    /// The `i`-th RNG uses the `AesCore` forked from `master` with stream ID
+    /// `i`, see `AesCore::fork_stream`. Their outputs are unrelated, and the
+    /// same master seed always yields the same RNGs.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn batch_from_seed(master: [u8; SEEDBYTES], n: usize) -> Vec<AesRng> {
+        let parent = AesCore::from_seed(master);
+        (0..n)
+            .map(|i| AesRngN(BlockRng::new(AesCoreN(parent.fork_stream(i as u64)))))
+            .collect()
+    }
+
+    /// Return an array of `N` random bytes, see `fill_bytes`.
+    ///
+    /// The array is zero-initialized first, which the compiler can usually
+    /// elide.
+    #[must_use]
+    pub fn generate_bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        self.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    /// Return an infinite iterator over random `u64` values.
+    ///
+    /// Use `AesRngIter::limit` to get a finite `ExactSizeIterator`.
+    pub fn iter_u64(&mut self) -> AesRngIter<'_> {
+        AesRngIter(self)
+    }
+
+    /// Return an infinite iterator over random `u32` values.
+    ///
+    /// Use `AesRngIterU32::limit` to get a finite `ExactSizeIterator`.
+    pub fn iter_u32(&mut self) -> AesRngIterU32<'_> {
+        AesRngIterU32(self)
+    }
+}
+
+impl<const N: usize> RngCore for AesRngN<N> {
+    #[inline(always)]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.core.0.fill(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.core.0.fill(dest);
+        Ok(())
+    }
+}
+
+impl<const N: usize> SeedableRng for AesRngN<N> {
+    type Seed = <AesCore as SeedableRng>::Seed;
+
+    #[inline]
+    fn from_seed(seed: Self::Seed) -> Self {
+        AesRngN(BlockRng::<AesCoreN<N>>::from_seed(seed))
+    }
+
+    fn from_rng<R: RngCore>(rng: R) -> Result<Self, Error> {
+        BlockRng::<AesCoreN<N>>::from_rng(rng).map(AesRngN)
+    }
+}
+
+impl<const N: usize> CryptoRng for AesRngN<N> {}
+
+impl<const N: usize> AsRef<dyn RngCore> for AesRngN<N> {
+    fn as_ref(&self) -> &(dyn RngCore + 'static) {
+        self
+    }
+}
+
+impl<const N: usize> AsMut<dyn RngCore> for AesRngN<N> {
+    fn as_mut(&mut self) -> &mut (dyn RngCore + 'static) {
+        self
+    }
+}
+
+/// Shows the counter and the position in the buffer, but not the key or the
+/// buffered values.
+impl<const N: usize> fmt::Debug for AesRngN<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AesRngN")
+            .field("buffer_size", &N)
+            .field("core", &self.0.core.0)
+            .field("index", &self.0.index())
+            .field("remaining", &(N - self.0.index()))
+            .finish()
+    }
+}
+
+/// Create an RNG using the given key, with the counter starting at zero, see
+/// `AesCore::from`.
+///
+/// Panics if the AES instructions are not available.
+impl<const N: usize> From<[u8; 16]> for AesRngN<N> {
+    fn from(key: [u8; 16]) -> AesRngN<N> {
+        AesRngN(BlockRng::new(AesCoreN(AesCore::from(key))))
+    }
+}
+
+/// Create an RNG using the all-zero seed.
+///
+/// Using a fixed seed is insecure, because the output is predictable. This
+/// is only meant for initializing structs which are seeded later. Prefer
+/// `AesRng::from_entropy`.
+///
+/// Panics if the AES instructions are not available.
+impl<const N: usize> Default for AesRngN<N> {
+    fn default() -> AesRngN<N> {
+        AesRngN::from_seed([0; SEEDBYTES])
+    }
+}
+
+/// Reading never fails and always fills the whole buffer.
+///
+/// This allows to use the RNG as a source of random bytes with `std::io`, for
+/// example via `std::io::copy(&mut rng.take(n), &mut file)`. Note that every
+/// call erases the key, just like `fill_bytes`.
+#[cfg(feature = "std")]
+impl<const N: usize> io::Read for AesRngN<N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill_bytes(buf);
+        Ok(buf.len())
+    }
+}
+
+/// Erases the key and overwrites the buffered values.
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Zeroize for AesRngN<N> {
+    fn zeroize(&mut self) {
+        self.0.core.zeroize();
+        // The buffer is not accessible, replacing it drops and zeroizes it.
+        self.0 = BlockRng::new(self.0.core.clone());
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> ZeroizeOnDrop for AesRngN<N> {}
+
+#[cfg(test)]
+mod tests {
+    extern crate itertools;
+    extern crate rand;
+
+    use self::itertools::Itertools;
+
+    use super::*;
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    #[test]
+    fn aes_ni_available() {
+        assert_eq!(is_aes_ni_available(), is_x86_feature_detected!("aes"));
+    }
+
+    #[test]
+    fn new_if_available() {
+        assert_eq!(AesRng::new_if_available([0; 32]).is_some(), is_aes_available());
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn from_entropy() {
+        let mut a = AesRng::from_entropy().unwrap();
+        let mut b = AesRng::from_entropy().unwrap();
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn buffer_size() {
+        let mut a = AesRngN::<4>::from_seed([1; 32]);
+        let mut b = AesCore::from_seed([1; 32]);
+        let mut buf = [0; 16];
+        for _ in 0..3 {
+            b.fill(&mut buf);
+            for chunk in buf.chunks(4) {
+                let mut x = [0; 4];
+                x.copy_from_slice(chunk);
+                assert_eq!(a.next_u32(), u32::from_le_bytes(x));
+            }
+        }
+
+        let mut a = AesRngN::<256>::from_seed([1; 32]);
+        let mut b = AesRng::from_seed([1; 32]);
+        for _ in 0..32 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+
+    #[test]
+    fn new_keyed() {
+        let key = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let mut a = AesCore::new_keyed(&key, 0x0f0e0d0c0b0a09080706050403020100);
+        let mut b = AesCore::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+        assert_eq!(a, b);
+
+        let mut buf_a = [0; 64];
+        let mut buf_b = [0; 64];
+        a.fill(&mut buf_a);
+        b.fill(&mut buf_b);
+        assert_eq!(buf_a[..], buf_b[..]);
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(AesCore::default(), AesCore::from_seed([0; 32]));
+        let mut a = AesRng::default();
+        let mut b = AesRng::from_seed([0; 32]);
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(::core::mem::size_of::<AesCore>(), (ROUNDS + 1) * 16 + 16);
+    }
+
+    #[test]
+    fn from_seed() {
+        let rng = AesCore::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+        {
+            let mut hex = String::new();
+            for &key in rng.round_keys.iter() {
+                let buf = key.bytes();
+                hex.push_str(&format!("{:02x}", buf.iter().format("")));
+            }
+            let expected = "000102030405060708090a0b0c0d0e0fd6aa74fdd2af72fadaa678f1d6ab76feb692cf0b643dbdf1be9bc5006830b3feb6ff744ed2c2c9bf6c590cbf0469bf4147f7f7bc95353e03f96c32bcfd058dfd3caaa3e8a99f9deb50f3af57adf622aa5e390f7df7a69296a7553dc10aa31f6b14f9701ae35fe28c440adf4d4ea9c02647438735a41c65b9e016baf4aebf7ad2549932d1f08557681093ed9cbe2c974e13111d7fe3944a17f307a78b4d2b30c5";
+            assert_eq!(hex, expected);
+        }
+        {
+            let buf = rng.counter.bytes();
+            let hex = format!("{:02x}", buf.iter().format(""));
+            let expected = "000102030405060708090a0b0c0d0e0f";
+            assert_eq!(hex, expected);
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize() {
+        let mut rng = AesCore::from_seed([1; 32]);
+        rng.zeroize();
+        for key in rng.round_keys.iter().chain(Some(&rng.counter)) {
+            assert_eq!(key.bytes(), [0; 16]);
+        }
+
+        let mut results = Results([1; 4]);
+        results.zeroize();
+        assert_eq!(results.0, [0; 4]);
+
+        let mut rng = AesRng::from_seed([1; 32]);
+        rng.next_u32();
+        rng.zeroize();
+        assert_eq!(rng.0.index(), AESRNG_BUFSIZE);
+        assert_eq!(rng.0.core.0.round_keys[0].bytes(), [0; 16]);
+    }
+
+    #[test]
+    fn fill_aligned() {
+        #[repr(align(16))]
+        struct Aligned([u8; 208]);
+
+        let seed = [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ];
+        let mut a = AesCore::from_seed(seed);
+        let mut b = AesCore::from_seed(seed);
+        for _ in 0..2 {
+            let mut buf = Aligned([0; 208]);
+            let mut expected = vec![0; 208];
+            a.fill_aligned(&mut buf.0);
+            b.fill(&mut expected);
+            assert_eq!(&buf.0[..], &expected[..]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn fill_aligned_unaligned() {
+        #[repr(align(16))]
+        struct Aligned([u8; 48]);
+
+        let mut rng = AesCore::from_seed([0; 32]);
+        let mut buf = Aligned([0; 48]);
+        rng.fill_aligned(&mut buf.0[1..33]);
+    }
+
+    #[test]
+    fn eq() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        assert_ne!(a, AesCore::from_seed([2; 32]));
+
+        a.set_counter(1);
+        assert_ne!(a, b);
+        b.set_counter(1);
+        assert_eq!(a, b);
+
+        a.fill(&mut []);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn counter() {
+        let mut a = AesCore::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+        assert_eq!(a.get_counter(), 0x0f0e0d0c0b0a09080706050403020100);
+        let mut b = a.clone();
+
+        let mut buf_a = [0; 48];
+        a.fill(&mut buf_a);
+        assert_eq!(a.get_counter(), 0x0f0e0d0c0b0a09080706050403020103);
+
+        let mut buf_b = [0; 16];
+        let position = b.get_counter() + 2;
+        b.set_counter(position);
+        assert_eq!(b.get_counter(), position);
+        b.fill(&mut buf_b);
+        assert_eq!(buf_a[32..], buf_b);
+
+        b.set_counter(!0);
+        assert_eq!(b.get_counter(), !0);
+
+        let mut c = a.clone();
+        let mut d = a.clone();
+        let start = c.get_counter();
+        c.advance_counter(3);
+        assert_eq!(c.get_counter(), start + 3);
+        d.set_counter(start + 3);
+        assert_eq!(c, d);
+        c.advance_counter(u64::MAX);
+        assert_eq!(c.get_counter(), start + 2);
+    }
+
+    #[test]
+    fn counter_u128() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let b = a.clone();
+        let v = a.get_counter();
+        assert_eq!(v, u128::from_le_bytes([1; 16]));
+        a.set_counter(v ^ (1 << 100) ^ 1);
+        assert_eq!(a.get_counter(), v ^ (1 << 100) ^ 1);
+        a.set_counter(v);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn counter_wrap() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let start = (5 << 64) | (u64::MAX as u128 - 2);
+        a.set_counter(start);
+        let mut b = a.clone();
+
+        let mut buf = [0; 16 * 6];
+        a.fill_fast(&mut buf);
+        assert_eq!(a.get_counter(), (5 << 64) | 3);
+        let blocks: Vec<&[u8]> = buf.chunks(16).collect();
+        for (i, x) in blocks.iter().enumerate() {
+            assert!(blocks[i + 1..].iter().all(|y| x != y));
+        }
+
+        // Filling across the wrap-around gives the same blocks as filling
+        // up to it and continuing from the wrapped counter.
+        let mut expected = [0; 16 * 6];
+        b.fill_fast(&mut expected[..16 * 3]);
+        assert_eq!(b.get_counter(), 5 << 64);
+        b.fill_fast(&mut expected[16 * 3..]);
+        assert_eq!(buf[..], expected[..]);
+    }
+
+    #[test]
+    fn fill_saturating() {
+        let mut a = AesCore::from_seed([1; 32]);
+        a.set_counter((5 << 64) | (u64::MAX as u128 - 2));
+        let mut b = a.clone();
+
+        // The key is erased at the wrap-around, so the blocks after it are the
+        // same as for a separate `fill` from the wrapped counter.
+        let mut buf = [0; 16 * 5 + 7];
+        a.fill_saturating(&mut buf);
+        assert_eq!(a.get_counter(), (5 << 64) | 3);
+        let mut expected = [0; 16 * 5 + 7];
+        b.fill(&mut expected[..16 * 3]);
+        assert_eq!(b.get_counter(), 5 << 64);
+        b.fill(&mut expected[16 * 3..]);
+        assert_eq!(buf[..], expected[..]);
+        assert_eq!(a, b);
+
+        // Without a wrap-around, this is the same as `fill`.
+        let mut c = a.clone();
+        a.fill_saturating(&mut buf);
+        c.fill(&mut expected);
+        assert_eq!(buf[..], expected[..]);
+        assert_eq!(a, c);
+
+        // Filling exactly up to the wrap-around does not split the buffer.
+        a.set_counter(u64::MAX as u128 - 1);
+        let mut d = a.clone();
+        a.fill_saturating(&mut buf[..32]);
+        d.fill(&mut expected[..32]);
+        assert_eq!(buf[..32], expected[..32]);
+        assert_eq!(a, d);
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn reseed_from_os() {
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = a.clone();
+        a.next_u32();
+        a.reseed_from_os().unwrap();
+        let x: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        let y: Vec<u64> = (0..4).map(|_| b.next_u64()).collect();
+        assert_ne!(x, y);
+
+        let mut core = AesCore::from_seed([1; 32]);
+        let old = core.clone();
+        core.reseed_from_os().unwrap();
+        assert_ne!(core, old);
+    }
+
+    #[test]
+    fn generate_bytes() {
+        fn check<const N: usize>() {
+            let mut a = AesRng::from_seed([1; 32]);
+            let mut b = a.clone();
+            let bytes: [u8; N] = a.generate_bytes();
+            let mut expected = [0; N];
+            b.fill_bytes(&mut expected);
+            assert_eq!(bytes, expected);
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        check::<1>();
+        check::<16>();
+        check::<17>();
+        check::<32>();
+        check::<128>();
+        check::<129>();
+    }
+
+    #[test]
+    fn clone_mid_buffer() {
+        let mut a = AesRng::from_seed([1; 32]);
+        for _ in 0..5 {
+            a.next_u32();
+        }
+        let mut b = a.clone();
+        // Cross the end of the buffer, so the clone also has to generate
+        // the same next block.
+        let expected: Vec<u32> = (0..100).map(|_| a.next_u32()).collect();
+        let actual: Vec<u32> = (0..100).map(|_| b.next_u32()).collect();
+        assert_eq!(actual, expected);
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn reseed() {
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut c = a.clone();
+        let mut d = a.clone();
+        a.reseed(b"some entropy from the environment");
+        b.reseed(b"some entropy from the environment");
+        c.reseed(b"other entropy from the environment");
+        d.reseed(&[]);
+        let x = a.next_u64();
+        assert_eq!(x, b.next_u64());
+        assert_ne!(x, c.next_u64());
+        assert_ne!(x, d.next_u64());
+        assert_ne!(x, AesRng::from_seed([1; 32]).next_u64());
+    }
+
+    #[test]
+    fn fork_stream() {
+        let rng = AesCore::from_seed([1; 32]);
+        let mut a = rng.fork_stream(0);
+        let mut b = rng.fork_stream(1);
+        let mut c = rng.fork_stream(1);
+        assert_eq!(a.counter.bytes(), [0; 16]);
+
+        let mut buf_a = vec![0; 100];
+        let mut buf_b = vec![0; 100];
+        let mut buf_c = vec![0; 100];
+        a.fill(&mut buf_a);
+        b.fill(&mut buf_b);
+        c.fill(&mut buf_c);
+        assert_ne!(buf_a, buf_b);
+        assert_eq!(buf_b, buf_c);
+    }
+
+    #[test]
+    fn fork_stream_key_is_not_output() {
+        let parents = [
+            AesCore::from_seed([1; 32]),
+            AesRng::from_u64_seed(0).0.core.0,
+            AesRng::from_u64_seed(7).0.core.0,
+            AesCore::new_keyed(&[1; 16], 3),
+        ];
+        for parent in &parents {
+            let mut output = [0; 1024];
+            parent.clone().fill(&mut output);
+            let mut erased = parent.clone();
+            erased.fill(&mut []);
+            for stream_id in 0..64 {
+                let key = parent.fork_stream(stream_id).round_keys[0].bytes();
+                assert!(output.chunks(16).all(|block| block != key));
+                assert_ne!(key, erased.round_keys[0].bytes());
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read() {
+        use std::io::Read;
+
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = AesRng::from_seed([1; 32]);
+        let mut buf_a = [0; 100];
+        let mut buf_b = [0; 100];
+        a.read_exact(&mut buf_a).unwrap();
+        b.fill_bytes(&mut buf_b);
+        assert_eq!(buf_a, buf_b);
+    }
+
+    #[test]
+    fn fill_u64() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut buf = [0u64; 32];
+        let mut expected = [0u8; 32 * 8];
+        a.fill(buf.as_byte_slice_mut());
+        buf.to_le();
+        b.fill(&mut expected);
+        for (&x, bytes) in buf.iter().zip(expected.chunks(8)) {
+            let mut y = [0; 8];
+            y.copy_from_slice(bytes);
+            assert_eq!(x, u64::from_le_bytes(y));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn fill_vec() {
+        use self::rand::Rng;
+
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut expected = vec![0; 200];
+        b.fill(&mut expected);
+        assert_eq!(a.fill_vec(200), expected);
+
+        let mut rng = AesRng::from_seed([1; 32]);
+        let mut boxed = vec![0u8; 200].into_boxed_slice();
+        rng.fill(&mut boxed[..]);
+        assert_eq!(&boxed[..], &expected[..]);
+    }
+
+    #[test]
+    fn fill_u32() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut buf = [0u32; 37];
+        let mut expected = [0u8; 37 * 4];
+        a.fill_u32(&mut buf);
+        b.fill(&mut expected);
+        for (&x, bytes) in buf.iter().zip(expected.chunks(4)) {
+            let mut y = [0; 4];
+            y.copy_from_slice(bytes);
+            assert_eq!(x, u32::from_le_bytes(y));
+        }
+
+        let mut c = a.clone();
+        let mut buf = [0u64; 9];
+        let mut expected = [0u8; 9 * 8];
+        a.fill_u64(&mut buf);
+        c.fill(&mut expected);
+        for (&x, bytes) in buf.iter().zip(expected.chunks(8)) {
+            let mut y = [0; 8];
+            y.copy_from_slice(bytes);
+            assert_eq!(x, u64::from_le_bytes(y));
+        }
+
+        a.fill_u32(&mut []);
+    }
+
+    #[test]
+    fn fill_u128() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut buf = [0u128; 32];
+        let mut expected = [0u8; 32 * 16];
+        a.fill(buf.as_byte_slice_mut());
+        buf.to_le();
+        b.fill(&mut expected);
+        for (&x, bytes) in buf.iter().zip(expected.chunks(16)) {
+            let mut y = [0; 16];
+            y.copy_from_slice(bytes);
+            assert_eq!(x, u128::from_le_bytes(y));
+        }
+    }
+
+    #[test]
+    fn fill_unrolled() {
+        fn check<const UNROLL: usize>() {
+            let mut a = AesCore::from_seed([1; 32]);
+            let mut b = a.clone();
+            for &len in &[0, 1, 16, 33, 100, 128, 300, 1000] {
+                let mut buf = vec![0; len];
+                let mut expected = vec![0; len];
+                a.fill_unrolled::<UNROLL>(&mut buf);
+                b.fill(&mut expected);
+                assert_eq!(buf, expected);
+                assert_eq!(a, b);
+            }
+        }
+        check::<1>();
+        check::<2>();
+        check::<4>();
+        check::<8>();
+        check::<16>();
+    }
+
+    #[test]
+    fn fill_fast() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut buf = [0; 100];
+        let mut expected = [0; 100];
+        a.fill_fast(&mut buf[..37]);
+        a.fill_fast(&mut buf[48..]);
+        b.fill(&mut expected);
+        assert_eq!(buf[..37], expected[..37]);
+        assert_eq!(buf[48..], expected[48..]);
+        assert_eq!(a.get_counter(), b.get_counter());
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn fill_with_scratch() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut scratch = FillScratch::new();
+        for &len in &[0, 7, 16, 33, 200, 1000] {
+            let mut buf = vec![0; len];
+            let mut expected = vec![0; len];
+            a.fill_with_scratch(&mut buf, &mut scratch);
+            b.fill(&mut expected);
+            assert_eq!(buf, expected);
+            assert_eq!(a, b);
+        }
+        scratch.clear();
+        assert!(scratch.r.iter().all(|x| x.bytes() == [0; 16]));
+    }
+
+    #[cfg(feature = "kdf")]
+    #[test]
+    fn try_from_slice() {
+        use core::convert::TryFrom;
+
+        assert_eq!(AesCore::try_from(&[][..]), Err(EmptyInput));
+        let key_material: Vec<u8> = (0..20).collect();
+        let seed = [
+            0xf8, 0xd6, 0x5b, 0x4d, 0x7e, 0x24, 0x0a, 0x83, 0x65, 0xf7, 0xe1, 0x14, 0x95, 0x54,
+            0x69, 0xd1, 0xde, 0x05, 0x23, 0x1c, 0xa0, 0xc6, 0x74, 0x89, 0x42, 0x24, 0xd7, 0x9c,
+            0xb2, 0x7e, 0x96, 0xf1,
+        ];
+        assert_eq!(AesCore::try_from(&key_material[..]), Ok(AesCore::from_seed(seed)));
+    }
+
+    #[test]
+    fn empty_byte_slice() {
+        let mut empty: [u32; 0] = [];
+        assert!(empty.as_byte_slice_mut().is_empty());
+        let mut empty: [u128; 0] = [];
+        assert!(empty.as_byte_slice_mut().is_empty());
+
+        let mut rng = AesCore::from_seed([1; 32]);
+        rng.fill_u32(&mut []);
+        rng.fill_u64(&mut []);
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(AesCore::PERIOD_BITS, 64);
+        assert_eq!(AesCore::OUTPUT_BLOCK_BYTES, ::core::mem::size_of::<M128>());
+        assert_eq!(AesRng::KEY_BITS as usize, 8 * ::core::mem::size_of::<M128>());
+    }
+
+    #[test]
+    fn from_seed_slice() {
+        let seed: Vec<u8> = (0..40).collect();
+        let mut array = [0; 32];
+        array.copy_from_slice(&seed[..32]);
+        let mut a = AesRng::from_seed_slice(&seed[..32]).unwrap();
+        let mut b = AesRng::from_seed(array);
+        assert_eq!(a.next_u64(), b.next_u64());
+
+        let err = AesRng::from_seed_slice(&seed).unwrap_err();
+        assert_eq!(err, LengthError { expected: 32, got: 40 });
+        assert_eq!(err.to_string(), "the seed must be 32 bytes long, but got 40 bytes");
+        assert!(AesRng::from_seed_slice(&[]).is_err());
+    }
+
+    #[test]
+    fn derive_key() {
+        let rng = AesCore::from_seed([1; 32]);
+        let derive = |label: &[u8]| {
+            let mut rng = rng.clone();
+            let mut key = [0; 32];
+            rng.derive_key(label, &mut key);
+            key
+        };
+        assert_eq!(derive(b"encryption"), derive(b"encryption"));
+        assert_ne!(derive(b"encryption"), derive(b"authentication"));
+        assert_ne!(derive(b""), derive(b"\0"));
+        assert_ne!(derive(b"ab"), derive(b"ab\0\0\0\0\0\0\0\0\0\0\0\0\0\0ab"));
+        // These would collide when XOR-folding the label into 16 bytes.
+        let mut x = [0; 17];
+        let mut y = [0; 17];
+        x[0] = 1;
+        x[16] = 2;
+        y[0] = 3;
+        assert_ne!(derive(&x), derive(&y));
+
+        let mut a = rng.clone();
+        let mut b = rng.clone();
+        b.absorb(&1u64.to_le_bytes());
+        b.absorb(b"x");
+        let mut expected = [0; 20];
+        b.fill(&mut expected);
+        let mut key = [0; 20];
+        a.derive_key(b"x", &mut key);
+        assert_eq!(key, expected);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn aes_rng_64() {
+        let mut rng = AesRng64::from_seed([1; 32]);
+        let mut core = AesCore::from_seed([1; 32]);
+        for _ in 0..3 {
+            let mut expected = [0u32; 16];
+            core.fill_u32(&mut expected);
+            for &x in &expected {
+                assert_eq!(rng.next_u32(), x);
+            }
+        }
+    }
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AesCore>();
+        assert_send_sync::<AesRng>();
+        assert_send_sync::<AesRng64>();
+        assert_send_sync::<AesCore256>();
+        assert_send_sync::<AesRng256>();
+    }
+
+    #[test]
+    fn encrypt_block_ecb() {
+        // Example from FIPS 197, appendix B.
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let plaintext = [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37,
+            0x07, 0x34,
+        ];
+        let ciphertext = [
+            0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb, 0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a,
+            0x0b, 0x32,
+        ];
+        let core = AesCore::new_keyed(&key, 0);
+        assert_eq!(core.encrypt_block(&plaintext), ciphertext);
+        assert_eq!(core.decrypt_block(&ciphertext), plaintext);
+
+        let mut core = AesCore::from_seed([1; 32]);
+        core.fill(&mut [0; 40]);
+        for i in 0..16 {
+            let block = [i; 16];
+            assert_eq!(core.decrypt_block(&core.encrypt_block(&block)), block);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn fill_into_writer() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut out = Vec::new();
+        a.fill_into_writer(&mut out, 10000).unwrap();
+        assert_eq!(out.len(), 10000);
+        for chunk in out.chunks(4096) {
+            assert_eq!(chunk, &b.fill_vec(chunk.len())[..]);
+        }
+        assert_eq!(a, b);
+
+        a.fill_into_writer(&mut out, 0).unwrap();
+        assert_eq!(out.len(), 10000);
+        let mut full = [0; 100];
+        assert!(a.fill_into_writer(&mut &mut full[..], 101).is_err());
+    }
+
+    #[test]
+    fn debug() {
+        let core = AesCore::new_keyed(&[1; 16], 0x0123456789abcdef);
+        assert_eq!(format!("{:?}", core), "AesCore { counter: 0x00000000000000000123456789abcdef }");
+
+        let mut rng = AesRng::from([1; 16]);
+        rng.next_u64();
+        rng.next_u32();
+        assert_eq!(
+            format!("{:?}", rng),
+            "AesRngN { buffer_size: 32, core: AesCore { counter: 0x00000000000000000000000000000008 }, index: 3, remaining: 29 }",
+        );
+    }
+
+    #[test]
+    fn warmup() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        a.warmup(0);
+        assert_eq!(a, b);
+        a.warmup(3);
+        for _ in 0..3 {
+            b.fill(&mut [0; 16]);
+        }
+        assert_eq!(a, b);
+    }
+
+    /// Fill `blocks` consecutive 128-byte buffers, panicking if two adjacent
+    /// buffers are equal.
+    fn assert_no_consecutive_repeats(rng: &mut AesCore, blocks: usize) {
+        let mut prev = [0; 128];
+        rng.fill(&mut prev);
+        for i in 1..blocks {
+            let mut buf = [0; 128];
+            rng.fill(&mut buf);
+            assert_ne!(buf[..], prev[..], "buffer {} repeats the previous one", i);
+            prev = buf;
+        }
+    }
+
+    #[test]
+    fn no_consecutive_repeats() {
+        assert_no_consecutive_repeats(&mut AesCore::from_seed([0; 32]), 1000);
+        assert_no_consecutive_repeats(&mut AesCore::new_keyed(&[0; 16], u128::from(u64::MAX)), 1000);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn fill_bytes_aligned() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        for &len in &[0, 1, 63, 64, 1000] {
+            let buf = a.fill_bytes_aligned(len);
+            assert_eq!(buf.as_ptr() as usize % 64, 0);
+            assert_eq!(&buf[..], &b.fill_vec(len)[..]);
+        }
+    }
+
+    #[test]
+    fn fill_with_nonce() {
+        let key = [7; 16];
+        let mut a = [0; 64];
+        let mut b = [0; 64];
+        super::fill_with_nonce(&key, 0, &mut a);
+        super::fill_with_nonce(&key, 1, &mut b);
+        for x in a.chunks(16) {
+            for y in b.chunks(16) {
+                assert_ne!(x, y);
+            }
+        }
+
+        let mut expected = [0; 64];
+        AesCore::new_keyed(&key, 1 << 64).fill(&mut expected);
+        assert_eq!(b[..], expected[..]);
+    }
+
+    #[test]
+    fn generate_matches_fill() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = AesCore::from_seed([1; 32]);
+        for _ in 0..3 {
+            let mut results = [0u32; AESRNG_BUFSIZE];
+            a.generate(&mut results);
+            let mut bytes = [0u8; 4 * AESRNG_BUFSIZE];
+            b.fill(&mut bytes);
+            // The results are the little-endian words of the output, on all
+            // platforms.
+            for (x, chunk) in results.iter().zip(bytes.chunks(4)) {
+                assert_eq!(x.to_le_bytes(), chunk);
+            }
+        }
+
+        let mut c = AesCore64::from_seed([1; 32]);
+        let mut results = Results::default();
+        c.generate(&mut results);
+        let mut bytes = [0u8; 64];
+        AesCore::from_seed([1; 32]).fill(&mut bytes);
+        for (x, chunk) in results.0.iter().zip(bytes.chunks(4)) {
+            assert_eq!(x.to_le_bytes(), chunk);
+        }
+    }
+
+    #[cfg(feature = "passphrase")]
+    #[test]
+    fn from_passphrase() {
+        let seed = [
+            0xae, 0x15, 0xca, 0x63, 0x03, 0x72, 0x1e, 0x88, 0x83, 0x64, 0x78, 0x29, 0xd3, 0x51,
+            0xe7, 0x83, 0x23, 0xe9, 0xe6, 0x05, 0x4e, 0xea, 0x5f, 0x0c, 0x69, 0x18, 0x89, 0xd8,
+            0xdc, 0xe1, 0xf0, 0xf6,
+        ];
+        let core = AesCore::from_passphrase("correct horse battery staple", b"aesrng salt", 1000);
+        assert_eq!(core, AesCore::from_seed(seed));
+    }
+
+    #[test]
+    fn set_key() {
+        let mut core = AesCore::new_keyed(&[1; 16], 0);
+        core.fill(&mut [0; 40]);
+        let counter = core.get_counter();
+        core.set_key(&[2; 16]);
+        assert_eq!(core.get_counter(), counter);
+        assert_eq!(core, AesCore::new_keyed(&[2; 16], counter));
+    }
+
+    #[test]
+    fn as_rng_core() {
+        fn next(rng: &mut dyn RngCore) -> u64 {
+            rng.next_u64()
+        }
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = AesRng::from_seed([1; 32]);
+        assert_eq!(next(a.as_rng_core()), b.next_u64());
+        assert_eq!(next(a.as_mut()), b.next_u64());
+    }
+
+    #[test]
+    fn from_hex_seed() {
+        let mut seed = [0; 32];
+        for (i, x) in seed.iter_mut().enumerate() {
+            *x = (i as u8).wrapping_mul(7).wrapping_add(0xa0);
+        }
+        let hex = "a0a7aeb5bcc3cad1d8dfe6edf4fb020910171e252c333a41484f565d646b7279";
+        assert_eq!(AesRng::from_hex_seed(hex).unwrap().0.core.0, AesCore::from_seed(seed));
+        let upper = hex.to_uppercase();
+        assert_eq!(AesRng::from_hex_seed(&upper).unwrap().0.core.0, AesCore::from_seed(seed));
+
+        let err = |reason| Err(HexDecodeError { reason });
+        assert_eq!(AesRng::from_hex_seed(&hex[1..]).map(|_| ()), err(HexErrorKind::OddLength));
+        assert_eq!(AesRng::from_hex_seed(&hex[2..]).map(|_| ()), err(HexErrorKind::WrongLength));
+        let invalid = hex.replacen("a0", "g0", 1);
+        assert_eq!(AesRng::from_hex_seed(&invalid).map(|_| ()), err(HexErrorKind::InvalidCharacter));
+    }
+
+    #[test]
+    fn fill_into_array() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = AesCore::from_seed([1; 32]);
+        assert_eq!(a.fill_into_array::<0>(), [0u8; 0]);
+        b.fill(&mut []);
+        assert_eq!(a, b);
+        let mut byte = [0; 1];
+        b.fill(&mut byte);
+        assert_eq!(a.fill_into_array::<1>(), byte);
+        let mut buffer = [0; 128];
+        b.fill(&mut buffer);
+        assert_eq!(a.fill_into_array::<128>(), buffer);
+    }
+
+    #[test]
+    fn verify_key_erasure() {
+        fn keys(core: &AesCore) -> Vec<[u8; 16]> {
+            core.round_keys.iter().map(|k| k.bytes()).collect()
+        }
+
+        let mut core = AesCore::from_seed([1; 32]);
+        let initial = keys(&core);
+        let mut seen = vec![initial.clone()];
+        // Cover the tail handling and, if available, the VAES path.
+        for &len in &[16, 40, 256, 0] {
+            core.fill(&mut vec![0; len]);
+            let current = keys(&core);
+            for previous in &seen {
+                for (a, b) in current.iter().zip(previous) {
+                    assert_ne!(a, b);
+                }
+            }
+            seen.push(current);
+        }
+    }
+
+    #[test]
+    fn turbo_fill_128() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        for _ in 0..3 {
+            let mut buf = [0; 128];
+            let mut expected = [0; 128];
+            a.turbo_fill_128(&mut buf);
+            b.fill(&mut expected);
+            assert_eq!(buf[..], expected[..]);
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn crypto_rng() {
+        fn assert_crypto_rng<R: CryptoRng>() {}
+        assert_crypto_rng::<AesCore>();
+        assert_crypto_rng::<AesCore64>();
+        assert_crypto_rng::<BlockRng<AesCore>>();
+        assert_crypto_rng::<AesRng>();
+    }
+
+    #[test]
+    fn from_u64_seed() {
+        let mut seed = [0; 32];
+        seed[..8].copy_from_slice(&[8, 7, 6, 5, 4, 3, 2, 1]);
+        let mut a = AesRng::from_u64_seed(0x0102030405060708);
+        let mut b = AesRng::from_seed(seed);
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn fill_nonnull_bytes() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut buf = vec![0; 10_000];
+        let mut expected = vec![0; 10_000];
+        a.fill_nonnull_bytes(&mut buf);
+        b.fill(&mut expected);
+        assert!(buf.iter().all(|&x| x != 0));
+        assert!(expected.contains(&0));
+        for (&x, &y) in buf.iter().zip(&expected) {
+            if y != 0 {
+                assert_eq!(x, y);
+            }
+        }
+        a.fill_nonnull_bytes(&mut []);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_from_seed() {
+        use std::collections::HashSet;
+
+        let mut batch = AesRng::batch_from_seed([1; 32], 4);
+        assert_eq!(batch.len(), 4);
+        let mut blocks = HashSet::new();
+        for rng in &mut batch {
+            let mut buf = [0; 1024];
+            rng.fill_bytes(&mut buf);
+            for block in buf.chunks(16) {
+                assert!(blocks.insert(block.to_vec()));
+            }
+        }
+
+        let parent = AesCore::from_seed([1; 32]);
+        let mut a = AesRng::batch_from_seed([1; 32], 3).pop().unwrap();
+        let mut b: AesRng = AesRngN(BlockRng::new(AesCoreN(parent.fork_stream(2))));
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::HashSet;
+
+        let a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut set = HashSet::new();
+        assert!(set.insert(a.clone()));
+        assert!(!set.insert(b.clone()));
+        b.fill(&mut [0; 16]);
+        assert!(set.insert(b));
+        assert!(set.contains(&a));
+    }
+
+    #[test]
+    fn split() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut seed = [0; 32];
+        b.fill(&mut seed);
+        let child = a.split();
+        assert_eq!(a, b);
+        assert_eq!(child, AesCore::from_seed(seed));
+
+        let mut buf_parent = [0; 64];
+        let mut buf_child = [0; 64];
+        a.fill(&mut buf_parent);
+        child.clone().fill(&mut buf_child);
+        assert_ne!(buf_parent, buf_child);
+    }
+
+    #[test]
+    fn fill_i32() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut buf = [0i32; 32];
+        let mut expected = [0u8; 32 * 4];
+        a.fill(buf.as_byte_slice_mut());
+        buf.to_le();
+        b.fill(&mut expected);
+        for (&x, bytes) in buf.iter().zip(expected.chunks(4)) {
+            let mut y = [0; 4];
+            y.copy_from_slice(bytes);
+            assert_eq!(x, i32::from_le_bytes(y));
+        }
+    }
+
+    #[test]
+    fn mixed() {
+        use self::rand::Rng;
+        use self::rand::distributions::Uniform;
+
+        let mut rng = AesRng::from_seed([1; 32]);
+        let mut values = Vec::new();
+        let mut buf = [0; 40];
+        for i in 0..1000 {
+            values.push(rng.next_u64());
+            rng.fill_bytes(&mut buf);
+            for chunk in buf.chunks(8) {
+                let mut x = [0; 8];
+                x.copy_from_slice(chunk);
+                values.push(u64::from_le_bytes(x));
+            }
+            values.push(rng.gen());
+            values.push(rng.sample(Uniform::new(0, u64::MAX)));
+            if i % 3 == 0 {
+                rng.next_u32();
+            }
+        }
+        let n = values.len();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), n);
+    }
+
+    #[test]
+    fn fill_bytes_keeps_buffer() {
+        let mut a = AesRng::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut values = Vec::new();
+        let mut buf = [0; 12];
+        for i in 0..500 {
+            let x = a.next_u32();
+            // Within the first buffer, the buffered values are neither
+            // skipped nor reused.
+            if i < 32 {
+                assert_eq!(x, b.next_u32());
+            }
+            values.push(x);
+            a.fill_bytes(&mut buf);
+            values.extend(buf.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])));
+        }
+        let n = values.len();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), n);
+    }
+
+    // Covers the partial-block tail path, down to a 1-byte buffer. Note that
+    // Miri cannot run this yet, it does not implement `aeskeygenassist`.
+    #[test]
+    fn fill_tail() {
+        let rng = AesCore::from_seed([1; 32]);
+        let mut expected = [0; 16];
+        rng.clone().fill(&mut expected);
+        for len in 1..16 {
+            let mut buf = [0; 16];
+            rng.clone().fill(&mut buf[..len]);
+            assert_eq!(buf[..len], expected[..len]);
+            assert!(buf[len..].iter().all(|&x| x == 0));
+        }
+    }
+
+    #[test]
+    fn try_from_seed() {
+        assert_eq!(AesCore::try_from_seed([0; 32]), Err(WeakSeedError));
+        let mut seed = [0; 32];
+        seed[31] = 1;
+        assert_eq!(AesCore::try_from_seed(seed), Ok(AesCore::from_seed(seed)));
+    }
+
+    #[test]
+    fn key_expansion() {
+        fn hex(s: &str) -> [u8; 16] {
+            let mut bytes = [0; 16];
+            for (i, x) in bytes.iter_mut().enumerate() {
+                *x = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+            }
+            bytes
+        }
+
+        // Key expansion example from FIPS-197, appendix A.1.
+        let core = AesCore::from(hex("2b7e151628aed2a6abf7158809cf4f3c"));
+        assert_eq!(core.round_keys[1].bytes(), hex("a0fafe1788542cb123a339392a6c7605"));
+        assert_eq!(core.round_keys[10].bytes(), hex("d014f9a8c9ee2589e13f0cc8b6630ca6"));
+
+        // Cipher example from FIPS-197, appendix C.1.
+        let core = AesCore::from(hex("000102030405060708090a0b0c0d0e0f"));
+        let block = Block(hex("00112233445566778899aabbccddeeff")).to_m128i();
+        let c = unsafe { encrypt_block(&core.round_keys, block) };
+        assert_eq!(c.bytes(), hex("69c4e0d86a7b0430d8cdb78070b4c55a"));
+
+        // Encryptions of the zero block, including edge-case keys from the
+        // AESAVS variable key test.
+        let vectors = [
+            ("00000000000000000000000000000000", "66e94bd4ef8a2c3b884cfa59ca342b2e"),
+            ("ffffffffffffffffffffffffffffffff", "a1f6258c877d5fcd8964484538bfc92c"),
+            ("80000000000000000000000000000000", "0edd33d3c621e546455bd8ba1418bec8"),
+            ("00000000000000000000000000000001", "0545aad56da2a97c3663d1432a3d1c84"),
+            ("2b7e151628aed2a6abf7158809cf4f3c", "7df76b0c1ab899b33e42f047b91b546f"),
+        ];
+        for &(key, expected) in &vectors {
+            let core = AesCore::from(hex(key));
+            let c = unsafe { encrypt_block(&core.round_keys, M128::from((0, 0))) };
+            assert_eq!(c.bytes(), hex(expected), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn from_key() {
+        let key = [7; 16];
+        let core = AesCore::from(key);
+        assert_eq!(core, AesCore::new_keyed(&key, 0));
+        assert_eq!(core.get_counter(), 0);
+
+        let mut seed = [0; 32];
+        seed[..16].copy_from_slice(&key);
+        let mut a = AesRng::from(key);
+        let mut b = AesRng::from_seed(seed);
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn seed_from_fn() {
+        let rng = AesCore::seed_from_fn(|seed| -> Result<(), ()> {
+            *seed = [3; 32];
+            Ok(())
+        });
+        assert_eq!(rng, Ok(AesCore::from_seed([3; 32])));
+        let rng = AesCore::seed_from_fn(|_| Err("no entropy"));
+        assert_eq!(rng, Err("no entropy"));
+    }
+
+    #[test]
+    fn fill() {
+        let mut rng = AesCore::from_seed([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12, 13, 14, 15,
+        ]);
+
+        let mut buf = vec![0; 200];
+        rng.fill(&mut buf);
+        let hex = format!("{:02x}", buf.iter().format(""));
+        let expected = "ddc1766018f72b77a8218c6593de2788f2d1e380d80f0c4d0fc2c294167b8f54a891572bf85fa4c4577a0af946d8a7c0c0b7c4efc6c580ded5616d6c99e2012f37f3c0ccc8815a805fc312cc59ecf9bb77723f91877423bed3f5c2204b17f0cd440543c647c4d1c55b7a5700041484ed3680785e09f51a77845578d51c7276cc19de1941f33ad0112665e9771aba4e07a204537666a96d6f9089497ca50810f5007940a574ef767e6aa7dc1b657bea655e6969c424c173fa346fb6f88412db459c6c0f6fc4c8de91";
+        assert_eq!(hex, expected);
+    }
+
+    #[cfg(feature = "proptest")]
+    mod properties {
+        extern crate proptest;
+
+        use self::proptest::prelude::*;
+        use {AesCore, SeedableRng};
+
+        proptest::proptest! {
+            #[test]
+            fn no_repetition(seed in any::<[u8; 32]>()) {
+                let mut rng = AesCore::from_seed(seed);
+                let mut a = [0; 128];
+                let mut b = [0; 128];
+                rng.fill(&mut a);
+                rng.fill(&mut b);
+                prop_assert_ne!(&a[..], &b[..]);
+            }
+
+            #[test]
+            fn deterministic(seed in any::<[u8; 32]>()) {
+                let mut a = [0; 128];
+                let mut b = [0; 128];
+                AesCore::from_seed(seed).fill(&mut a);
+                AesCore::from_seed(seed).fill(&mut b);
+                prop_assert_eq!(&a[..], &b[..]);
+            }
+        }
+    }
+
+    /// Test vectors generated by `etc/aes_stream_vectors.c`, which follows the
+    /// C implementation this crate was ported from. See the comment at the
+    /// top of that file for how to compile it.
+    mod compat {
+        use {AesCore, SeedableRng};
+
+        fn check(seed: [u8; 32], expected: &str) {
+            let mut rng = AesCore::from_seed(seed);
+            let mut buf = [0; 256];
+            rng.fill(&mut buf[..128]);
+            rng.fill(&mut buf[128..]);
+            let hex: String = buf.iter().map(|x| format!("{:02x}", x)).collect();
+            assert_eq!(hex, expected);
+        }
+
+        #[test]
+        fn zero_seed() {
+            check([0; 32], "b28604b8ba32ba08f6494164b34df50d729521ce6cd099910ad5c152818d9b9d24315c2dfd35518975c1c213a301481d250638d91fd8565aff271058104013013c87db289600ca46c5f378e1fc6c6ed5ec8a6c7b6105300cbaf577b48b4724477dc845adb7f8308a5f9e1e7ce0fd6938626bdfdb88de4df336ef5a2171c237858ea1279d9601a8153cd0b4a191e541305441cda0b46a0633f256ced1dee261aea7b6c10ed46b33417ec38f9bceb7226c81ff02c3f2cfd061aa41d22a0b46372b28a6de3eda6a9a9bab5c0dc56ffb827f6d4ba05d854006b512610f49e297fa0685730f40a8f4f5daa5232d66aba974e3a0ae0546988535fdfd03ddbbf783c79a");
+        }
+
+        #[test]
+        fn ones_seed() {
+            check([0xff; 32], "b56852fbb2e2c6fedd5221506ddbcf1caf540f3d11c5dd14113d7f69da4ad7a81f88864a91a939547020c1f053aa05b3bec039fee9bcef32f6c090dc2e2660668a39f78e36afc09fec2ec207d8a4cf1c13465c33fd0bf0d3c36e315c6a307b90e73d40431fd4156bf26d3a60a4e83a70ea5c69363991ec2c180dc245f9b2af2d4030642c6381649f8a2386e78e79d25d5524a61fceb1f0e3ff326853b563f062e894a3dc69e79a975617271a0ff0bd63b7ab66c165c336dcc444f506aad8cd830673c1a042dabdb2201809a36cf8b208cf4481b2af6711daf15269abf20403e5c10d485cdeabc568f109c92936cf633ec18eaa4862b074eee168a81fdd61ff9e");
+        }
+
+        #[test]
+        fn mixed_seed() {
+            let mut seed = [0; 32];
+            for (i, x) in seed.iter_mut().enumerate() {
+                *x = (i * 37 + 11) as u8;
+            }
+            check(seed, "af5c91da8de3f49fe426b6d830f83f38cfff48844c0e214700c75af0cf4e6b6c3636710be845de921e4c0cccc6025446f7a46de72aa47b885a92fb9caa7bd01d706347a9a777ee1277fd43368ea9948d7f7e59c095cb2a8eb94f0aff3ce7ba071beb460c33241701a9d55d9f75e8064029ef5e3ba0f8eb9fb944f936f69d223b0c4d2777ea0e729bd16199ae7ed0e43113f544741132248507e12a981a07eca87efbb77e4959bf71daa21846855d8840278cba7f7af8a2126a7f56b3ab3496343313f6f16d0079443f376d4735368e44fc24cc997441fa213e2bb8199a4bf1231836f79254f0e514df35879a7cbd2f337a85c7c2d14caedd4678dde7e7d2475e");
+        }
     }
 }
@@ -0,0 +1,109 @@
+//! Heap allocations that are locked into memory, enabled by the `memlock`
+//! feature.
+
+use std::fmt;
+use std::io;
+use std::mem::{self, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+
+/// A box whose memory is locked, so it is not swapped to disk.
+///
+/// This is meant for keeping key material like the round keys of `AesCore`
+/// out of swap partitions. On Unix, the allocation is locked using `mlock`
+/// and unlocked using `munlock` after dropping the contents. On other
+/// platforms, memory is not locked and this behaves like a plain `Box`.
+///
+/// Memory is locked with page granularity. Unlocking unlocks the whole pages,
+/// even if they contain other locked allocations.
+pub struct LockedBox<T>(mem::ManuallyDrop<Box<T>>);
+
+impl<T> LockedBox<T> {
+    /// Move `value` to a new locked heap allocation.
+    ///
+    /// Returns an error if the memory cannot be locked, for example because
+    /// `RLIMIT_MEMLOCK` is exceeded.
+    pub fn new(value: T) -> io::Result<LockedBox<T>> {
+        let boxed = Box::new(value);
+        unsafe { lock(&*boxed as *const T as *const u8, mem::size_of::<T>())? };
+        Ok(LockedBox(mem::ManuallyDrop::new(boxed)))
+    }
+}
+
+impl<T> Deref for LockedBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for LockedBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> Drop for LockedBox<T> {
+    fn drop(&mut self) {
+        let boxed = unsafe { mem::ManuallyDrop::take(&mut self.0) };
+        let raw = Box::into_raw(boxed);
+        unsafe {
+            // Drop the contents (which may erase them) before unlocking.
+            ptr::drop_in_place(raw);
+            unlock(raw as *const u8, mem::size_of::<T>());
+            // Free the allocation without dropping the contents again.
+            drop(Box::from_raw(raw as *mut MaybeUninit<T>));
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for LockedBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LockedBox").field(&**self).finish()
+    }
+}
+
+#[cfg(unix)]
+unsafe fn lock(addr: *const u8, len: usize) -> io::Result<()> {
+    if ::libc::mlock(addr as *const ::libc::c_void, len) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+unsafe fn unlock(addr: *const u8, len: usize) {
+    // This can only fail for invalid ranges, there is nothing to do about it
+    // while dropping.
+    ::libc::munlock(addr as *const ::libc::c_void, len);
+}
+
+/// Memory locking is not implemented on this platform.
+#[cfg(not(unix))]
+unsafe fn lock(_addr: *const u8, _len: usize) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+unsafe fn unlock(_addr: *const u8, _len: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::SeedableRng;
+
+    use AesCore;
+    use super::LockedBox;
+
+    #[test]
+    fn locked_box() {
+        let mut locked = LockedBox::new(AesCore::from_seed([1; 32])).unwrap();
+        let mut core = AesCore::from_seed([1; 32]);
+        let mut buf_a = [0; 32];
+        let mut buf_b = [0; 32];
+        locked.fill(&mut buf_a);
+        core.fill(&mut buf_b);
+        assert_eq!(buf_a, buf_b);
+        assert_eq!(*locked, core);
+    }
+}
@@ -0,0 +1,255 @@
+//! AES128-OCB3 authenticated encryption, enabled by the `ocb` feature.
+//!
+//! This implements OCB3 as specified in RFC 7253, with 96-bit nonces and
+//! 128-bit tags, on top of `AesCore::encrypt_block` and
+//! `AesCore::decrypt_block`. The key is the current key of the given
+//! `AesCore`, for example one created by `AesCore::new_keyed`. Note that the
+//! key changes after each `fill`.
+//!
+//! ```
+//! use aesrng::AesCore;
+//! use aesrng::ocb::{ocb_open, ocb_seal};
+//!
+//! let core = AesCore::new_keyed(&[1; 16], 0);
+//! let nonce = [2; 12];
+//! let sealed = ocb_seal(&core, &nonce, b"attack at dawn", b"header");
+//! assert_eq!(ocb_open(&core, &nonce, &sealed, b"header").unwrap(), b"attack at dawn");
+//! assert!(ocb_open(&core, &nonce, &sealed, b"other header").is_err());
+//! ```
+
+use alloc::vec::Vec;
+
+use aead::{AuthError, TAG_SIZE};
+use AesCore;
+
+/// Encrypt and authenticate `plaintext`, and authenticate `aad`.
+///
+/// Returns the ciphertext followed by the 16-byte authentication tag. A nonce
+/// must never be reused with the same key.
+#[must_use]
+pub fn ocb_seal(core: &AesCore, nonce: &[u8; 12], plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    let ocb = Ocb::new(core);
+    let mut offset = ocb.initial_offset(nonce);
+    let mut checksum = 0;
+    let mut sealed = Vec::with_capacity(plaintext.len() + TAG_SIZE);
+    let mut blocks = plaintext.chunks_exact(16);
+    for (i, block) in (&mut blocks).enumerate() {
+        let p = read_block(block);
+        offset ^= ocb.l(i + 1);
+        let c = offset ^ ocb.encipher(p ^ offset);
+        sealed.extend_from_slice(&c.to_be_bytes());
+        checksum ^= p;
+    }
+    let rest = blocks.remainder();
+    if !rest.is_empty() {
+        offset ^= ocb.l_star;
+        let pad = ocb.encipher(offset).to_be_bytes();
+        sealed.extend(rest.iter().zip(&pad).map(|(p, k)| p ^ k));
+        checksum ^= pad_block(rest);
+    }
+    let tag = ocb.tag(checksum, offset, aad);
+    sealed.extend_from_slice(&tag.to_be_bytes());
+    sealed
+}
+
+/// Verify and decrypt the output of `ocb_seal`.
+///
+/// Returns an error if the ciphertext, the tag or `aad` were modified, or if
+/// the key or nonce are different from the ones used for sealing. Decrypting
+/// a block costs about twice as much as encrypting it, see
+/// `AesCore::decrypt_block`.
+pub fn ocb_open(core: &AesCore, nonce: &[u8; 12], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AuthError> {
+    if sealed.len() < TAG_SIZE {
+        return Err(AuthError);
+    }
+    let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_SIZE);
+    let ocb = Ocb::new(core);
+    let mut offset = ocb.initial_offset(nonce);
+    let mut checksum = 0;
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    let mut blocks = ciphertext.chunks_exact(16);
+    for (i, block) in (&mut blocks).enumerate() {
+        let c = read_block(block);
+        offset ^= ocb.l(i + 1);
+        let p = offset ^ ocb.decipher(c ^ offset);
+        plaintext.extend_from_slice(&p.to_be_bytes());
+        checksum ^= p;
+    }
+    let rest = blocks.remainder();
+    if !rest.is_empty() {
+        offset ^= ocb.l_star;
+        let pad = ocb.encipher(offset).to_be_bytes();
+        let start = plaintext.len();
+        plaintext.extend(rest.iter().zip(&pad).map(|(c, k)| c ^ k));
+        checksum ^= pad_block(&plaintext[start..]);
+    }
+    let expected = ocb.tag(checksum, offset, aad).to_be_bytes();
+    // Compare the tags in constant time.
+    let diff = expected.iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(AuthError);
+    }
+    Ok(plaintext)
+}
+
+/// The key-dependent values of OCB3. Blocks are big-endian integers.
+struct Ocb<'a> {
+    core: &'a AesCore,
+    l_star: u128,
+    l_dollar: u128,
+    /// `L_i` for `i` in `0..64`, enough for any number of blocks.
+    l: [u128; 64],
+}
+
+impl<'a> Ocb<'a> {
+    fn new(core: &'a AesCore) -> Ocb<'a> {
+        let l_star = encipher(core, 0);
+        let l_dollar = double(l_star);
+        let mut l = [0; 64];
+        l[0] = double(l_dollar);
+        for i in 1..l.len() {
+            l[i] = double(l[i - 1]);
+        }
+        Ocb { core, l_star, l_dollar, l }
+    }
+
+    fn encipher(&self, block: u128) -> u128 {
+        encipher(self.core, block)
+    }
+
+    fn decipher(&self, block: u128) -> u128 {
+        u128::from_be_bytes(self.core.decrypt_block(&block.to_be_bytes()))
+    }
+
+    /// Return the offset added for the block with the 1-based index `i`.
+    fn l(&self, i: usize) -> u128 {
+        self.l[i.trailing_zeros() as usize]
+    }
+
+    /// Compute `Offset_0` from the nonce.
+    fn initial_offset(&self, nonce: &[u8; 12]) -> u128 {
+        // The tag length modulo 128 (zero) in 7 bits, then zeros and a one
+        // bit padding the nonce to 128 bits.
+        let mut block = [0; 16];
+        block[3] = 1;
+        block[4..].copy_from_slice(nonce);
+        let nonce = u128::from_be_bytes(block);
+        let bottom = (nonce & 0x3f) as u32;
+        let ktop = self.encipher(nonce & !0x3f);
+        // The 192-bit `Stretch` is `ktop` followed by `stretch`.
+        let stretch = ((ktop >> 64) ^ (ktop >> 56)) as u64;
+        if bottom == 0 {
+            ktop
+        } else {
+            ktop << bottom | u128::from(stretch >> (64 - bottom))
+        }
+    }
+
+    /// Compute the tag from the plaintext checksum and the final offset.
+    fn tag(&self, checksum: u128, offset: u128, aad: &[u8]) -> u128 {
+        self.encipher(checksum ^ offset ^ self.l_dollar) ^ self.hash(aad)
+    }
+
+    /// Compute `HASH(K, A)`.
+    fn hash(&self, aad: &[u8]) -> u128 {
+        let mut sum = 0;
+        let mut offset = 0;
+        let mut blocks = aad.chunks_exact(16);
+        for (i, block) in (&mut blocks).enumerate() {
+            offset ^= self.l(i + 1);
+            sum ^= self.encipher(read_block(block) ^ offset);
+        }
+        let rest = blocks.remainder();
+        if !rest.is_empty() {
+            offset ^= self.l_star;
+            sum ^= self.encipher(pad_block(rest) ^ offset);
+        }
+        sum
+    }
+}
+
+fn encipher(core: &AesCore, block: u128) -> u128 {
+    u128::from_be_bytes(core.encrypt_block(&block.to_be_bytes()))
+}
+
+/// Multiply by x in GF(2^128), as defined in RFC 7253.
+fn double(block: u128) -> u128 {
+    (block << 1) ^ ((block >> 127) * 0x87)
+}
+
+fn read_block(bytes: &[u8]) -> u128 {
+    let mut block = [0; 16];
+    block.copy_from_slice(bytes);
+    u128::from_be_bytes(block)
+}
+
+/// Pad a partial block with a one bit followed by zeros.
+fn pad_block(bytes: &[u8]) -> u128 {
+    let mut block = [0; 16];
+    block[..bytes.len()].copy_from_slice(bytes);
+    block[bytes.len()] = 0x80;
+    u128::from_be_bytes(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use aead::AuthError;
+    use AesCore;
+    use super::{ocb_open, ocb_seal};
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn nonce(last: u8) -> [u8; 12] {
+        [0xbb, 0xaa, 0x99, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, last]
+    }
+
+    #[test]
+    fn rfc7253() {
+        // Sample results from appendix A of RFC 7253.
+        let mut key = [0; 16];
+        for (i, x) in key.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        let core = AesCore::new_keyed(&key, 0);
+
+        let sealed = ocb_seal(&core, &nonce(0x00), &[], &[]);
+        assert_eq!(sealed, hex("785407bfffc8ad9edcc5520ac9111ee6"));
+        assert_eq!(ocb_open(&core, &nonce(0x00), &sealed, &[]), Ok(vec![]));
+
+        let data: Vec<u8> = (0..40).collect();
+        let sealed = ocb_seal(&core, &nonce(0x01), &data[..8], &data[..8]);
+        assert_eq!(sealed, hex("6820b3657b6f615a5725bda0d3b4eb3a257c9af1f8f03009"));
+        assert_eq!(ocb_open(&core, &nonce(0x01), &sealed, &data[..8]), Ok(data[..8].to_vec()));
+
+        let sealed = ocb_seal(&core, &nonce(0x0d), &data, &data);
+        assert_eq!(sealed, hex("d5ca91748410c1751ff8a2f618255b68a0a12e093ff454606e59f9c1d0ddc54b65e8628e568bad7aed07ba06a4a69483a7035490c5769e60"));
+        assert_eq!(ocb_open(&core, &nonce(0x0d), &sealed, &data), Ok(data));
+
+        // Longer inputs, generated using the `cryptography` Python package.
+        let plaintext: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let aad: Vec<u8> = (0..100).collect();
+        let sealed = ocb_seal(&core, &nonce(0x10), &plaintext, &aad);
+        assert_eq!(sealed[..16], hex("f6b1cfe767ccee4e3c72e608909408c8")[..]);
+        assert_eq!(sealed[300..], hex("05f8c53b9775cb0c738089a948b2f854")[..]);
+        assert_eq!(ocb_open(&core, &nonce(0x10), &sealed, &aad), Ok(plaintext));
+    }
+
+    #[test]
+    fn tampering() {
+        let core = AesCore::new_keyed(&[7; 16], 0);
+        let nonce = [3; 12];
+        let sealed = ocb_seal(&core, &nonce, b"attack at dawn", b"header");
+        for i in 0..sealed.len() {
+            let mut modified = sealed.clone();
+            modified[i] ^= 1;
+            assert_eq!(ocb_open(&core, &nonce, &modified, b"header"), Err(AuthError));
+        }
+        assert_eq!(ocb_open(&core, &nonce, &sealed[1..], b"header"), Err(AuthError));
+        assert_eq!(ocb_open(&core, &nonce, &sealed[..15], b"header"), Err(AuthError));
+        assert_eq!(ocb_open(&core, &[4; 12], &sealed, b"header"), Err(AuthError));
+        let other = AesCore::new_keyed(&[8; 16], 0);
+        assert_eq!(ocb_open(&other, &nonce, &sealed, b"header"), Err(AuthError));
+    }
+}
@@ -0,0 +1,50 @@
+//! Filling large buffers on multiple threads, enabled by the `rayon` feature.
+
+use rayon::prelude::*;
+
+use AesCore;
+
+/// Fill `buffer` with random data in parallel, using chunks of `chunk_size`
+/// bytes.
+///
+/// A parent `AesCore` is split off from `rng`, which erases the key of `rng`.
+/// Each chunk is then filled on the Rayon thread pool by an `AesCore` forked
+/// from the parent, using the index of the chunk as the stream ID. The output
+/// only depends on the state of `rng` and on `chunk_size`, not on the number
+/// of threads. It differs from the output of `fill`.
+///
+/// Chunks should be large (e.g. 1 MiB), otherwise the overhead of the threads
+/// and of forking dominates.
+///
+/// Panics if `chunk_size` is zero.
+pub fn parallel_fill(rng: &mut AesCore, buffer: &mut [u8], chunk_size: usize) {
+    assert!(chunk_size > 0, "chunk size must not be zero");
+    let parent = rng.split();
+    buffer.par_chunks_mut(chunk_size).enumerate().for_each(|(i, chunk)| {
+        parent.fork_stream(i as u64).fill(chunk);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::SeedableRng;
+
+    use AesCore;
+    use super::parallel_fill;
+
+    #[test]
+    fn parallel_fill_matches_forks() {
+        let mut rng = AesCore::from_seed([1; 32]);
+        let parent = rng.clone().split();
+        let mut buf = vec![0; 10000];
+        parallel_fill(&mut rng, &mut buf, 4096);
+        for (i, chunk) in buf.chunks(4096).enumerate() {
+            let mut expected = vec![0; chunk.len()];
+            parent.fork_stream(i as u64).fill(&mut expected);
+            assert_eq!(chunk, &expected[..]);
+        }
+        let mut expected = AesCore::from_seed([1; 32]);
+        let _ = expected.split();
+        assert_eq!(rng, expected);
+    }
+}
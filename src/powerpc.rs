@@ -0,0 +1,39 @@
+//! AES rounds using the POWER8 crypto instructions, see `asm_m128`.
+
+/// Check whether the CPU supports the POWER AES instructions.
+///
+/// The `power8-crypto` and `power9-vector` target features are required at
+/// compile time. This always returns `true`.
+#[inline]
+pub fn is_aes_available() -> bool {
+    true
+}
+
+/// Apply one AES round, see `asm_m128`.
+///
+/// The POWER AES instructions number the bytes of the state from the most
+/// significant end of the vector register. `lxvb16x` and `stxvb16x` (POWER9)
+/// keep the bytes in memory order on both big and little endian. VSX
+/// registers 34 and 35 are the vector registers `v2` and `v3`.
+macro_rules! cipher_round {
+    (enc, $state:expr, $round_key:expr) => (cipher_round!("vcipher", $state, $round_key));
+    (enc_last, $state:expr, $round_key:expr) => (cipher_round!("vcipherlast", $state, $round_key));
+    (dec, $state:expr, $round_key:expr) => (cipher_round!("vncipher", $state, $round_key));
+    (dec_last, $state:expr, $round_key:expr) => (cipher_round!("vncipherlast", $state, $round_key));
+    ($insn:expr, $state:expr, $round_key:expr) => ({
+        let mut state = $state.0.to_le_bytes();
+        let round_key = $round_key.0.to_le_bytes();
+        ::core::arch::asm!(
+            "lxvb16x 34, 0, {state}",
+            "lxvb16x 35, 0, {round_key}",
+            concat!($insn, " 2, 2, 3"),
+            "stxvb16x 34, 0, {state}",
+            state = in(reg) state.as_mut_ptr(),
+            round_key = in(reg) round_key.as_ptr(),
+            out("v2") _,
+            out("v3") _,
+            options(nostack),
+        );
+        M128(u128::from_le_bytes(state))
+    });
+}
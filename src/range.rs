@@ -0,0 +1,152 @@
+//! Sampling integers uniformly from a range, and shuffling slices.
+
+use rand_core::RngCore;
+
+use AesRng;
+
+/// Integer types supported by `gen_range`.
+///
+/// This is implemented for `u32`, `u64` and `i64`.
+pub trait UniformInt: Copy + PartialOrd {
+    /// Return `hi - lo` as an unsigned integer, assuming `lo < hi`.
+    #[doc(hidden)]
+    fn span(lo: Self, hi: Self) -> u64;
+
+    /// Return `lo + offset`, assuming the result is in range.
+    #[doc(hidden)]
+    fn add_offset(lo: Self, offset: u64) -> Self;
+}
+
+impl UniformInt for u32 {
+    fn span(lo: u32, hi: u32) -> u64 {
+        u64::from(hi - lo)
+    }
+
+    fn add_offset(lo: u32, offset: u64) -> u32 {
+        lo + offset as u32
+    }
+}
+
+impl UniformInt for u64 {
+    fn span(lo: u64, hi: u64) -> u64 {
+        hi - lo
+    }
+
+    fn add_offset(lo: u64, offset: u64) -> u64 {
+        lo + offset
+    }
+}
+
+impl UniformInt for i64 {
+    fn span(lo: i64, hi: i64) -> u64 {
+        hi.wrapping_sub(lo) as u64
+    }
+
+    fn add_offset(lo: i64, offset: u64) -> i64 {
+        lo.wrapping_add(offset as i64)
+    }
+}
+
+/// Sample an integer uniformly from the half-open range `[lo, hi)`.
+///
+/// This uses rejection sampling on `next_u64`: a value is rejected if it is
+/// smaller than 2^64 mod (hi - lo), which would otherwise bias the result.
+/// If the size of the range is a power of two, nothing is rejected. Otherwise
+/// the probability of a rejection is below (hi - lo) / 2^64, so the expected
+/// number of rejections is negligible unless the range spans a large part of
+/// the `u64` values. In the worst case, a range of 2^63 + 1 values, about
+/// half of the values are rejected, i.e. about one rejection is expected per
+/// sample.
+///
+/// Panics if `lo >= hi`.
+pub fn gen_range<T: UniformInt>(rng: &mut AesRng, lo: T, hi: T) -> T {
+    assert!(lo < hi, "empty range");
+    let span = T::span(lo, hi);
+    // 2^64 mod span, the number of values that have to be rejected.
+    let threshold = span.wrapping_neg() % span;
+    loop {
+        let x = rng.next_u64();
+        if x >= threshold {
+            return T::add_offset(lo, x % span);
+        }
+    }
+}
+
+impl AesRng {
+    /// Shuffle the slice in place, using the Fisher-Yates algorithm.
+    ///
+    /// All permutations are equally likely. The indices are sampled using
+    /// `gen_range`, so there is no modulo bias.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = gen_range(self, 0, i as u64 + 1);
+            slice.swap(i, j as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand_core::SeedableRng;
+
+    use AesRng;
+    use super::gen_range;
+
+    #[test]
+    fn bounds() {
+        let mut rng = AesRng::from_seed([1; 32]);
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            let x = gen_range(&mut rng, 10u32, 20);
+            assert!((10..20).contains(&x));
+            seen[(x - 10) as usize] = true;
+        }
+        assert!(seen.iter().all(|&x| x));
+
+        for _ in 0..1000 {
+            let x = gen_range(&mut rng, -3i64, 2);
+            assert!((-3..2).contains(&x));
+            let x = gen_range(&mut rng, u64::MAX - 5, u64::MAX);
+            assert!((u64::MAX - 5..u64::MAX).contains(&x));
+        }
+        assert_eq!(gen_range(&mut rng, 7u64, 8), 7);
+        let x = gen_range(&mut rng, i64::MIN, i64::MAX);
+        assert!(x < i64::MAX);
+    }
+
+    #[test]
+    fn shuffle() {
+        const SHUFFLES: u32 = 100_000;
+        let mut rng = AesRng::from_seed([1; 32]);
+        let mut counts = HashMap::new();
+        for _ in 0..SHUFFLES {
+            let mut slice = [0, 1, 2, 3, 4];
+            rng.shuffle(&mut slice);
+            *counts.entry(slice).or_insert(0u32) += 1;
+        }
+        assert_eq!(counts.len(), 120);
+
+        // Chi-square test with 119 degrees of freedom. The critical value for
+        // a significance level of 0.001 is about 172.4.
+        let expected = f64::from(SHUFFLES) / 120.;
+        let chi_square: f64 = counts.values()
+            .map(|&n| (f64::from(n) - expected).powi(2) / expected)
+            .sum();
+        assert!(chi_square < 172.4, "chi-square: {}", chi_square);
+
+        let mut empty: [u8; 0] = [];
+        rng.shuffle(&mut empty);
+        let mut single = [1];
+        rng.shuffle(&mut single);
+        assert_eq!(single, [1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_range() {
+        let mut rng = AesRng::from_seed([1; 32]);
+        gen_range(&mut rng, 1u32, 1);
+    }
+}
@@ -0,0 +1,211 @@
+//! A portable reference implementation of AES128 for testing.
+//!
+//! This uses the S-box table from FIPS 197 and works on plain byte arrays, so
+//! unlike the SIMD implementation it can be run under Miri. It is slow and not
+//! constant-time.
+
+use ROUNDS;
+
+/// The AES S-box, see FIPS 197, figure 7.
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// Round keys, in the same byte order as the SIMD round keys.
+pub type RoundKeys = [[u8; 16]; ROUNDS + 1];
+
+/// Multiply by x in GF(2^8).
+fn xtime(x: u8) -> u8 {
+    (x << 1) ^ (if x & 0x80 != 0 { 0x1b } else { 0 })
+}
+
+fn sub_bytes(state: &mut [u8; 16]) {
+    for x in state.iter_mut() {
+        *x = SBOX[*x as usize];
+    }
+}
+
+/// Rotate row `i` of the column-major state left by `i`.
+fn shift_rows(state: &mut [u8; 16]) {
+    let s = *state;
+    for col in 0..4 {
+        for row in 0..4 {
+            state[4 * col + row] = s[4 * ((col + row) % 4) + row];
+        }
+    }
+}
+
+fn mix_columns(state: &mut [u8; 16]) {
+    for col in state.chunks_exact_mut(4) {
+        let a = [col[0], col[1], col[2], col[3]];
+        let all = a[0] ^ a[1] ^ a[2] ^ a[3];
+        for i in 0..4 {
+            col[i] = a[i] ^ all ^ xtime(a[i] ^ a[(i + 1) % 4]);
+        }
+    }
+}
+
+fn add_round_key(state: &mut [u8; 16], round_key: &[u8; 16]) {
+    for (x, k) in state.iter_mut().zip(round_key) {
+        *x ^= k;
+    }
+}
+
+/// Expand an AES128 key, see FIPS 197, section 5.2.
+pub fn expand_key(key: &[u8; 16]) -> RoundKeys {
+    let mut w = [[0u8; 4]; 4 * (ROUNDS + 1)];
+    for (i, word) in key.chunks_exact(4).enumerate() {
+        w[i].copy_from_slice(word);
+    }
+    let mut rcon = 1u8;
+    for i in 4..w.len() {
+        let mut t = w[i - 1];
+        if i % 4 == 0 {
+            t = [SBOX[t[1] as usize] ^ rcon, SBOX[t[2] as usize], SBOX[t[3] as usize], SBOX[t[0] as usize]];
+            rcon = xtime(rcon);
+        }
+        for j in 0..4 {
+            w[i][j] = w[i - 4][j] ^ t[j];
+        }
+    }
+    let mut round_keys = [[0; 16]; ROUNDS + 1];
+    for (round_key, words) in round_keys.iter_mut().zip(w.chunks_exact(4)) {
+        for (dst, word) in round_key.chunks_exact_mut(4).zip(words) {
+            dst.copy_from_slice(word);
+        }
+    }
+    round_keys
+}
+
+/// Apply rounds `first..=last` of the cipher to the state.
+fn rounds(round_keys: &RoundKeys, state: &mut [u8; 16], first: usize, last: usize) {
+    for round_key in &round_keys[first..=last] {
+        sub_bytes(state);
+        shift_rows(state);
+        mix_columns(state);
+        add_round_key(state, round_key);
+    }
+}
+
+/// Encrypt a block using AES128, see FIPS 197, section 5.1.
+pub fn encrypt_block(round_keys: &RoundKeys, block: &[u8; 16]) -> [u8; 16] {
+    let mut state = *block;
+    add_round_key(&mut state, &round_keys[0]);
+    rounds(round_keys, &mut state, 1, ROUNDS - 1);
+    sub_bytes(&mut state);
+    shift_rows(&mut state);
+    add_round_key(&mut state, &round_keys[ROUNDS]);
+    state
+}
+
+/// Compute a block of RNG output for the given counter block, like
+/// `compute_rounds!`.
+///
+/// This is AES128 with the state after round 5 fed forward into the output.
+pub fn rng_block(round_keys: &RoundKeys, counter: &[u8; 16]) -> [u8; 16] {
+    let mut state = *counter;
+    add_round_key(&mut state, &round_keys[0]);
+    rounds(round_keys, &mut state, 1, 5);
+    let feed_forward = state;
+    rounds(round_keys, &mut state, 6, ROUNDS - 1);
+    sub_bytes(&mut state);
+    shift_rows(&mut state);
+    add_round_key(&mut state, &round_keys[ROUNDS]);
+    add_round_key(&mut state, &feed_forward);
+    state
+}
+
+/// Fill `buffer` like `AesCore::fill` without the key erasure, starting at
+/// the given counter.
+pub fn fill(round_keys: &RoundKeys, counter: u128, buffer: &mut [u8]) {
+    let mut counter = counter;
+    for chunk in buffer.chunks_mut(16) {
+        let block = rng_block(round_keys, &counter.to_le_bytes());
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        let low = (counter as u64).wrapping_add(1);
+        counter = (counter & !u128::from(u64::MAX)) | u128::from(low);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encrypt_block, expand_key, fill};
+    use AesCore;
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+
+    #[test]
+    fn fips197() {
+        // Key expansion and example from FIPS 197, appendices A.1 and B.
+        let round_keys = expand_key(&KEY);
+        assert_eq!(round_keys[10], [
+            0xd0, 0x14, 0xf9, 0xa8, 0xc9, 0xee, 0x25, 0x89, 0xe1, 0x3f, 0x0c, 0xc8, 0xb6, 0x63,
+            0x0c, 0xa6,
+        ]);
+        let plaintext = [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37,
+            0x07, 0x34,
+        ];
+        assert_eq!(encrypt_block(&round_keys, &plaintext), [
+            0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb, 0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a,
+            0x0b, 0x32,
+        ]);
+    }
+
+    // The following tests compare with the SIMD implementation, which Miri
+    // cannot run.
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn key_expansion() {
+        for i in 0..16 {
+            let key = [i * 17; 16];
+            let core = AesCore::new_keyed(&key, 0);
+            let expected = expand_key(&key);
+            for (round_key, expected) in core.round_keys.iter().zip(expected.iter()) {
+                assert_eq!(round_key.bytes(), *expected);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn block_encryption() {
+        let core = AesCore::new_keyed(&KEY, 0);
+        let round_keys = expand_key(&KEY);
+        for i in 0..=255 {
+            let block = [i; 16];
+            assert_eq!(core.encrypt_block(&block), encrypt_block(&round_keys, &block));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn rng_output() {
+        let counter = (7 << 64) | u128::from(u64::MAX - 3);
+        let mut core = AesCore::new_keyed(&KEY, counter);
+        let mut buf = [0; 200];
+        core.fill_fast(&mut buf);
+        let mut expected = [0; 200];
+        fill(&expand_key(&KEY), counter, &mut expected);
+        assert_eq!(buf[..], expected[..]);
+    }
+}
@@ -0,0 +1,41 @@
+//! AES rounds using the RISC-V vector AES extension (Zvkned), see `asm_m128`.
+
+/// Check whether the CPU supports the RISC-V vector AES instructions.
+///
+/// The `zvkned` target feature is required at compile time, because runtime
+/// detection is not available on stable Rust. This always returns `true`.
+#[inline]
+pub fn is_aes_available() -> bool {
+    true
+}
+
+/// Apply one AES round, see `asm_m128`.
+///
+/// The state and round key are 128-bit element groups of four 32-bit
+/// elements.
+macro_rules! cipher_round {
+    (enc, $state:expr, $round_key:expr) => (cipher_round!("vaesem.vs", $state, $round_key));
+    (enc_last, $state:expr, $round_key:expr) => (cipher_round!("vaesef.vs", $state, $round_key));
+    (dec, $state:expr, $round_key:expr) => (cipher_round!("vaesdm.vs", $state, $round_key));
+    (dec_last, $state:expr, $round_key:expr) => (cipher_round!("vaesdf.vs", $state, $round_key));
+    ($insn:expr, $state:expr, $round_key:expr) => ({
+        let mut state = $state.0.to_le_bytes();
+        let round_key = $round_key.0.to_le_bytes();
+        ::core::arch::asm!(
+            ".option push",
+            ".option arch, +zvkned",
+            "vsetivli zero, 4, e32, m1, ta, ma",
+            "vle32.v v8, ({state})",
+            "vle32.v v9, ({round_key})",
+            concat!($insn, " v8, v9"),
+            "vse32.v v8, ({state})",
+            ".option pop",
+            state = in(reg) state.as_mut_ptr(),
+            round_key = in(reg) round_key.as_ptr(),
+            out("v8") _,
+            out("v9") _,
+            options(nostack),
+        );
+        M128(u128::from_le_bytes(state))
+    });
+}
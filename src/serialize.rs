@@ -0,0 +1,186 @@
+//! Serialization of the RNG state, enabled by the `serde1` feature.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+
+use {AesCore, Block, M128, ROUNDS, Results, is_aes_available};
+
+/// Version of the serialized representation of `AesCore`.
+///
+/// This has to be incremented whenever `AesCoreState` changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Serialized representation of `AesCore`.
+#[derive(Serialize, Deserialize)]
+struct AesCoreState {
+    version: u32,
+    round_keys: [[u8; 16]; ROUNDS + 1],
+    counter: [u8; 16],
+}
+
+impl Serialize for AesCore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut round_keys = [[0; 16]; ROUNDS + 1];
+        for (bytes, key) in round_keys.iter_mut().zip(self.round_keys.iter()) {
+            *bytes = key.bytes();
+        }
+        AesCoreState {
+            version: FORMAT_VERSION,
+            round_keys,
+            counter: self.counter.bytes(),
+        }.serialize(serializer)
+    }
+}
+
+/// Fails if the AES instructions are not available, or if the state was
+/// serialized using an unknown format version.
+impl<'de> Deserialize<'de> for AesCore {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AesCore, D::Error> {
+        AesCore::try_deserialize(deserializer)?.map_err(de::Error::custom)
+    }
+}
+
+impl AesCore {
+    /// Deserialize an `AesCore`, reporting unknown format versions
+    /// separately.
+    ///
+    /// The serialized state contains the version of its format, which changes
+    /// whenever the internal representation changes. Returns the error of the
+    /// deserializer if the input is malformed, and `Ok(Err(VersionMismatch))`
+    /// if it was serialized using a different format version. The
+    /// `Deserialize` impl fails in both cases.
+    pub fn try_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Result<AesCore, VersionMismatch>, D::Error> {
+        let state = AesCoreState::deserialize(deserializer)?;
+        if state.version != FORMAT_VERSION {
+            return Ok(Err(VersionMismatch { expected: FORMAT_VERSION, got: state.version }));
+        }
+        if !is_aes_available() {
+            return Err(de::Error::custom("AES instructions are not available"));
+        }
+        let zero = M128::from((0, 0));
+        let mut round_keys = [zero; ROUNDS + 1];
+        for (key, bytes) in round_keys.iter_mut().zip(state.round_keys.iter()) {
+            *key = Block(*bytes).to_m128i();
+        }
+        let counter = Block(state.counter).to_m128i();
+        Ok(Ok(AesCore {
+            round_keys,
+            counter,
+        }))
+    }
+}
+
+/// Error returned by `AesCore::try_deserialize` for states serialized using
+/// an unknown format version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// The format version supported by this version of the crate.
+    pub expected: u32,
+    /// The format version of the serialized state.
+    pub got: u32,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected serialization format version {}, but got version {}", self.expected, self.got)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for VersionMismatch {}
+
+impl<const N: usize> Serialize for Results<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for x in self.0.iter() {
+            tuple.serialize_element(x)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Results<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Results<N>, D::Error> {
+        struct ResultsVisitor<const N: usize>(PhantomData<Results<N>>);
+
+        impl<'de, const N: usize> Visitor<'de> for ResultsVisitor<N> {
+            type Value = Results<N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an array of length {}", N)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Results<N>, A::Error> {
+                let mut results = Results::default();
+                for (i, x) in results.0.iter_mut().enumerate() {
+                    *x = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                }
+                Ok(results)
+            }
+        }
+
+        deserializer.deserialize_tuple(N, ResultsVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use rand_core::{RngCore, SeedableRng};
+
+    use {AesCore, AesRng};
+    use super::VersionMismatch;
+
+    #[test]
+    fn aes_core() {
+        let mut a = AesCore::from_seed([1; 32]);
+        let json = serde_json::to_string(&a).unwrap();
+        let mut b: AesCore = serde_json::from_str(&json).unwrap();
+
+        let mut buf_a = [0; 64];
+        let mut buf_b = [0; 64];
+        a.fill(&mut buf_a);
+        b.fill(&mut buf_b);
+        assert_eq!(buf_a[..], buf_b[..]);
+    }
+
+    #[test]
+    fn version() {
+        let a = AesCore::from_seed([1; 32]);
+        let json = serde_json::to_string(&a).unwrap();
+        assert!(json.starts_with("{\"version\":1,"));
+        let b = AesCore::try_deserialize(&mut serde_json::Deserializer::from_str(&json));
+        assert_eq!(b.unwrap(), Ok(a));
+
+        let json = json.replacen("\"version\":1", "\"version\":2", 1);
+        let b = AesCore::try_deserialize(&mut serde_json::Deserializer::from_str(&json));
+        assert_eq!(b.unwrap(), Err(VersionMismatch { expected: 1, got: 2 }));
+        assert!(serde_json::from_str::<AesCore>(&json).is_err());
+
+        let json = json.replacen("\"version\":2,", "", 1);
+        assert!(AesCore::try_deserialize(&mut serde_json::Deserializer::from_str(&json)).is_err());
+    }
+
+    #[test]
+    fn aes_rng() {
+        let mut a = AesRng::from_seed([1; 32]);
+        // Make sure the buffer is partially consumed.
+        a.next_u32();
+        let json = serde_json::to_string(&a).unwrap();
+        let mut b: AesRng = serde_json::from_str(&json).unwrap();
+
+        for _ in 0..64 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+        let mut buf_a = [0; 64];
+        let mut buf_b = [0; 64];
+        a.fill_bytes(&mut buf_a);
+        b.fill_bytes(&mut buf_b);
+        assert_eq!(buf_a[..], buf_b[..]);
+    }
+}
@@ -0,0 +1,92 @@
+//! An RNG that can be shared between threads.
+
+use std::sync::{Arc, Mutex};
+
+use rand_core::{CryptoRng, RngCore, SeedableRng, Error};
+
+use AesCore;
+
+/// A handle to an `AesCore` that can be shared between threads.
+///
+/// Cloning the handle is cheap, all clones use the same underlying
+/// `AesCore`. Every call locks a mutex and erases the key, so `next_u32` and
+/// `next_u64` are a lot slower than for `AesRng`, and threads using the same
+/// RNG contend for the lock. For high throughput, prefer to seed an `AesRng`
+/// per thread, for example from a `SharedAesRng` using `SeedableRng::from_rng`.
+#[derive(Clone, Debug)]
+pub struct SharedAesRng(Arc<Mutex<AesCore>>);
+
+impl From<AesCore> for SharedAesRng {
+    fn from(core: AesCore) -> SharedAesRng {
+        SharedAesRng(Arc::new(Mutex::new(core)))
+    }
+}
+
+impl RngCore for SharedAesRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.lock().unwrap().fill(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for SharedAesRng {
+    type Seed = <AesCore as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> SharedAesRng {
+        AesCore::from_seed(seed).into()
+    }
+}
+
+impl CryptoRng for SharedAesRng {}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rand_core::{RngCore, SeedableRng};
+
+    use AesCore;
+    use super::SharedAesRng;
+
+    #[test]
+    fn shared() {
+        let mut a = SharedAesRng::from_seed([1; 32]);
+        let mut b = a.clone();
+        let mut core = AesCore::from_seed([1; 32]);
+
+        let mut buf = [0; 8];
+        core.fill(&mut buf[..4]);
+        assert_eq!(a.next_u32().to_le_bytes(), buf[..4]);
+        core.fill(&mut buf);
+        assert_eq!(b.next_u64().to_le_bytes(), buf);
+    }
+
+    #[test]
+    fn threads() {
+        let rng = SharedAesRng::from_seed([1; 32]);
+        let handles: Vec<_> = (0..4).map(|_| {
+            let mut rng = rng.clone();
+            thread::spawn(move || rng.next_u64())
+        }).collect();
+        let mut values: Vec<u64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), 4);
+    }
+}
@@ -1,7 +1,49 @@
 #[cfg(target_arch = "x86")]
-use std::arch::x86::*;
+use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use core::arch::x86_64::*;
+
+use block::Block;
+
+/// Check whether the CPU supports the AES-NI instructions.
+///
+/// This queries CPUID at most once, the result is cached afterwards. If the
+/// `aes` target feature is enabled at compile time, it always returns `true`.
+#[inline]
+pub fn is_aes_ni_available() -> bool {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    const UNKNOWN: usize = 0;
+    const UNAVAILABLE: usize = 1;
+    const AVAILABLE: usize = 2;
+    static AES_NI: AtomicUsize = AtomicUsize::new(UNKNOWN);
+
+    if cfg!(target_feature = "aes") {
+        return true;
+    }
+    match AES_NI.load(Ordering::Relaxed) {
+        UNAVAILABLE => false,
+        AVAILABLE => true,
+        _ => {
+            let available = cpuid_aes();
+            AES_NI.store(if available { AVAILABLE } else { UNAVAILABLE }, Ordering::Relaxed);
+            available
+        }
+    }
+}
+
+/// Query CPUID leaf 1 for the AES bit (ECX bit 25).
+///
+/// CPUID is supported by any CPU implementing SSE2, which `M128` requires.
+#[allow(unused_unsafe)]  // `__cpuid` is safe on recent Rust versions.
+fn cpuid_aes() -> bool {
+    unsafe {
+        if __cpuid(0).eax < 1 {
+            return false;
+        }
+        __cpuid_count(1, 0).ecx & (1 << 25) != 0
+    }
+}
 
 /// SIMD type used by the fast-key erasure RNG.
 ///
@@ -20,30 +62,65 @@ impl M128 {
         _mm_storeu_si128(mem_addr as *mut __m128i, self.0);
     }
 
-    #[allow(unused)]  // Only used in tests.
+    /// Like `store`, but `mem_addr` must be 16-byte aligned.
+    #[inline]
+    pub unsafe fn store_aligned(&self, mem_addr: *mut u8) {
+        _mm_store_si128(mem_addr as *mut __m128i, self.0);
+    }
+
     #[inline]
     pub fn bytes(&self) -> [u8; 16] {
+        Block::from_m128i(*self).0
+    }
+
+    /// Add as 128-bit little-endian integers, unlike `Add`, which adds the
+    /// upper and lower 64 bits separately.
+    #[inline]
+    pub fn add128(self, rhs: M128) -> M128 {
         unsafe {
-            #[repr(align(16))]
-            struct Aligned([u8; 16]);
-            let mut buf = ::std::mem::MaybeUninit::<Aligned>::uninit();
-            self.store(buf.as_mut_ptr() as *mut u8);
-            buf.assume_init().0
+            let (a, b) = (self.0, rhs.0);
+            let sum = _mm_add_epi64(a, b);
+            // The most significant bit of `(a & b) | ((a | b) & !sum)` is the
+            // carry out of each half. Only the one of the lower half is kept,
+            // moved to the least significant bit of the upper half.
+            let carry = _mm_or_si128(_mm_and_si128(a, b), _mm_andnot_si128(sum, _mm_or_si128(a, b)));
+            let carry = _mm_slli_si128(_mm_srli_epi64(carry, 63), 8);
+            M128(_mm_add_epi64(sum, carry))
         }
     }
 
     #[inline]
-    pub fn encrypt(self, round_key: M128) -> M128 {
-        unsafe { M128(_mm_aesenc_si128(self.0, round_key.0)) }
+    #[target_feature(enable = "aes")]
+    pub unsafe fn encrypt(self, round_key: M128) -> M128 {
+        M128(_mm_aesenc_si128(self.0, round_key.0))
+    }
+
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn encrypt_last(self, round_key: M128) -> M128 {
+        M128(_mm_aesenclast_si128(self.0, round_key.0))
     }
 
     #[inline]
-    pub fn encrypt_last(self, round_key: M128) -> M128 {
-        unsafe { M128(_mm_aesenclast_si128(self.0, round_key.0)) }
+    #[target_feature(enable = "aes")]
+    pub unsafe fn decrypt(self, round_key: M128) -> M128 {
+        M128(_mm_aesdec_si128(self.0, round_key.0))
+    }
+
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn decrypt_last(self, round_key: M128) -> M128 {
+        M128(_mm_aesdeclast_si128(self.0, round_key.0))
+    }
+
+    #[inline]
+    #[target_feature(enable = "aes")]
+    pub unsafe fn inv_mix_columns(self) -> M128 {
+        M128(_mm_aesimc_si128(self.0))
     }
 }
 
-impl ::std::ops::BitXor<M128> for M128 {
+impl ::core::ops::BitXor<M128> for M128 {
     type Output = M128;
 
     #[inline]
@@ -52,43 +129,45 @@ impl ::std::ops::BitXor<M128> for M128 {
     }
 }
 
+// The following macros have to be used in an unsafe context.
+
 macro_rules! shiftl {
     ($a:expr, $imm8:expr) => ({
         #[cfg(target_arch = "x86")]
-        use std::arch::x86::_mm_slli_si128;
+        use core::arch::x86::_mm_slli_si128;
         #[cfg(target_arch = "x86_64")]
-        use std::arch::x86_64::_mm_slli_si128;
+        use core::arch::x86_64::_mm_slli_si128;
 
         let M128(a) = $a;
-        unsafe { M128(_mm_slli_si128(a, $imm8)) }
+        M128(_mm_slli_si128(a, $imm8))
     });
 }
 
 macro_rules! shuffle {
     ($a:expr, $imm8:expr) => ({
         #[cfg(target_arch = "x86")]
-        use std::arch::x86::_mm_shuffle_epi32;
+        use core::arch::x86::_mm_shuffle_epi32;
         #[cfg(target_arch = "x86_64")]
-        use std::arch::x86_64::_mm_shuffle_epi32;
+        use core::arch::x86_64::_mm_shuffle_epi32;
 
         let M128(a) = $a;
-        unsafe { M128(_mm_shuffle_epi32(a, $imm8)) }
+        M128(_mm_shuffle_epi32(a, $imm8))
     });
 }
 
 macro_rules! keygenassist {
     ($a:expr, $imm8:expr) => ({
         #[cfg(target_arch = "x86")]
-        use std::arch::x86::_mm_aeskeygenassist_si128;
+        use core::arch::x86::_mm_aeskeygenassist_si128;
         #[cfg(target_arch = "x86_64")]
-        use std::arch::x86_64::_mm_aeskeygenassist_si128;
+        use core::arch::x86_64::_mm_aeskeygenassist_si128;
 
         let M128(a) = $a;
-        unsafe { M128(_mm_aeskeygenassist_si128(a, $imm8)) }
+        M128(_mm_aeskeygenassist_si128(a, $imm8))
     });
 }
 
-impl ::std::ops::Add<M128> for M128 {
+impl ::core::ops::Add<M128> for M128 {
     type Output = M128;
 
     #[inline]
@@ -97,14 +176,14 @@ impl ::std::ops::Add<M128> for M128 {
     }
 }
 
-impl ::std::convert::From<(i64, i64)> for M128 {
+impl ::core::convert::From<(i64, i64)> for M128 {
     #[inline]
     fn from(x: (i64, i64)) -> M128 {
         unsafe { M128(_mm_set_epi64x(x.0, x.1)) }
     }
 }
 
-impl ::std::convert::From<__m128i> for M128 {
+impl ::core::convert::From<__m128i> for M128 {
     #[inline]
     fn from(x: __m128i) -> M128 {
         M128(x)
@@ -0,0 +1,86 @@
+//! A lazily-initialized RNG per thread, enabled by the `getrandom` feature.
+
+use core::cell::RefCell;
+use core::ops::{Deref, DerefMut};
+
+use AesRng;
+
+thread_local!(
+    #[allow(clippy::missing_const_for_thread_local)]  // `const` initializers require Rust 1.59.
+    static THREAD_RNG: RefCell<Option<AesRng>> = RefCell::new(None)
+);
+
+/// Guard giving access to the thread-local `AesRng`.
+///
+/// The RNG is moved out of the thread-local storage while the guard is alive
+/// and moved back when it is dropped.
+struct ThreadLocalRng(Option<AesRng>);
+
+impl Deref for ThreadLocalRng {
+    type Target = AesRng;
+
+    fn deref(&self) -> &AesRng {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for ThreadLocalRng {
+    fn deref_mut(&mut self) -> &mut AesRng {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for ThreadLocalRng {
+    fn drop(&mut self) {
+        let rng = self.0.take();
+        // If the thread-local storage is being destroyed, the RNG is dropped.
+        let _ = THREAD_RNG.try_with(|cell| {
+            let mut slot = cell.borrow_mut();
+            if slot.is_none() {
+                *slot = rng;
+            }
+        });
+    }
+}
+
+/// Return the `AesRng` of the current thread.
+///
+/// The RNG is seeded from the operating system's random-number generator on
+/// first access. If the RNG of the current thread is already in use, a newly
+/// seeded RNG is returned instead.
+///
+/// Panics if the seed cannot be obtained or if the AES instructions are not
+/// available.
+#[must_use]
+pub fn thread_local_rng() -> impl DerefMut<Target = AesRng> {
+    let rng = THREAD_RNG.try_with(|cell| cell.borrow_mut().take()).ok().flatten();
+    let rng = rng.unwrap_or_else(|| {
+        AesRng::from_entropy().expect("failed to seed thread-local RNG")
+    });
+    ThreadLocalRng(Some(rng))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::RngCore;
+
+    use super::thread_local_rng;
+
+    #[test]
+    fn thread_local() {
+        let a = thread_local_rng().next_u64();
+        let b = thread_local_rng().next_u64();
+        assert_ne!(a, b);
+
+        let mut outer = thread_local_rng();
+        let mut inner = thread_local_rng();
+        assert_ne!(outer.next_u64(), inner.next_u64());
+    }
+
+    #[test]
+    fn threads() {
+        let a = thread_local_rng().next_u64();
+        let b = ::std::thread::spawn(|| thread_local_rng().next_u64()).join().unwrap();
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,119 @@
+//! AES128 in XTS mode, as used for storage encryption.
+
+use core::fmt;
+
+use {AesCore, Block, M128, encrypt_block};
+
+/// Size of a sector encrypted by `AesXts::encrypt_sector`, in bytes.
+const SECTOR_SIZE: usize = 512;
+
+/// AES128 in XTS mode, as specified in IEEE 1619.
+///
+/// This is not meant for generating random numbers, but shows how the AES
+/// primitives of this crate can be used as a building block. Like `AesCtr`,
+/// it uses the plain AES block cipher and does not erase the keys.
+///
+/// Each block of a sector is encrypted using the first key, after XORing it
+/// with a tweak before and after the encryption. The tweak is derived by
+/// encrypting the sector number using the second key, and multiplied by the
+/// primitive element of GF(2^128) for each block.
+#[derive(Clone)]
+pub struct AesXts {
+    key1: AesCore,
+    key2: AesCore,
+}
+
+impl AesXts {
+    /// Create a new `AesXts` using the given data and tweak keys.
+    ///
+    /// The keys should be different, see IEEE 1619.
+    ///
+    /// Panics if the AES instructions are not available.
+    #[must_use]
+    pub fn new(key1: &[u8; 16], key2: &[u8; 16]) -> AesXts {
+        AesXts {
+            key1: AesCore::new_keyed(key1, 0),
+            key2: AesCore::new_keyed(key2, 0),
+        }
+    }
+
+    /// Encrypt the given sector in place.
+    ///
+    /// The sector number is used as the little-endian data unit sequence
+    /// number.
+    pub fn encrypt_sector(&mut self, sector: u64, data: &mut [u8; SECTOR_SIZE]) {
+        // An `AesXts` can only be created if the AES instructions are
+        // available.
+        unsafe { self.encrypt_sector_impl(sector, data) }
+    }
+
+    #[cfg_attr(not(any(target_arch = "riscv64", target_arch = "powerpc64")), target_feature(enable = "aes"))]
+    unsafe fn encrypt_sector_impl(&mut self, sector: u64, data: &mut [u8; SECTOR_SIZE]) {
+        let sector = Block((sector as u128).to_le_bytes()).to_m128i();
+        let mut tweak = encrypt_block(&self.key2.round_keys, sector);
+        for chunk in data.chunks_exact_mut(16) {
+            let p = Block::from_slice(chunk).to_m128i() ^ tweak;
+            let c = encrypt_block(&self.key1.round_keys, p) ^ tweak;
+            c.store(chunk.as_mut_ptr());
+            tweak = mul_alpha(tweak);
+        }
+    }
+}
+
+/// Multiply the tweak by the primitive element of GF(2^128), using the
+/// little-endian convention of IEEE 1619.
+fn mul_alpha(tweak: M128) -> M128 {
+    let t = u128::from_le_bytes(tweak.bytes());
+    let t = (t << 1) ^ ((t >> 127) * 0x87);
+    Block(t.to_le_bytes()).to_m128i()
+}
+
+// Custom Debug implementation that does not expose the internal state
+impl fmt::Debug for AesXts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AesXts {{}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AesXts, SECTOR_SIZE};
+
+    const KEY1: [u8; 16] = [
+        0x27, 0x18, 0x28, 0x18, 0x28, 0x45, 0x90, 0x45, 0x23, 0x53, 0x60, 0x28, 0x74, 0x71, 0x35,
+        0x26,
+    ];
+    const KEY2: [u8; 16] = [
+        0x31, 0x41, 0x59, 0x26, 0x53, 0x58, 0x97, 0x93, 0x23, 0x84, 0x62, 0x64, 0x33, 0x83, 0x27,
+        0x95,
+    ];
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn plaintext() -> [u8; SECTOR_SIZE] {
+        let mut data = [0; SECTOR_SIZE];
+        for (i, x) in data.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        data
+    }
+
+    #[test]
+    fn ieee1619() {
+        // Test vector 4 from IEEE 1619.
+        let mut data = plaintext();
+        let expected = hex("27a7479befa1d476489f308cd4cfa6e2a96e4bbe3208ff25287dd3819616e89cc78cf7f5e543445f8333d8fa7f56000005279fa5d8b5e4ad40e736ddb4d35412328063fd2aab53e5ea1e0a9f332500a5df9487d07a5c92cc512c8866c7e860ce93fdf166a24912b422976146ae20ce846bb7dc9ba94a767aaef20c0d61ad02655ea92dc4c4e41a8952c651d33174be51a10c421110e6d81588ede82103a252d8a750e8768defffed9122810aaeb99f9172af82b604dc4b8e51bcb08235a6f4341332e4ca60482a4ba1a03b3e65008fc5da76b70bf1690db4eae29c5f1badd03c5ccf2a55d705ddcd86d449511ceb7ec30bf12b1fa35b913f9f747a8afd1b130e94bff94effd01a91735ca1726acd0b197c4e5b03393697e126826fb6bbde8ecc1e08298516e2c9ed03ff3c1b7860f6de76d4cecd94c8119855ef5297ca67e9f3e7ff72b1e99785ca0a7e7720c5b36dc6d72cac9574c8cbbc2f801e23e56fd344b07f22154beba0f08ce8891e643ed995c94d9a69c9f1b5f499027a78572aeebd74d20cc39881c213ee770b1010e4bea718846977ae119f7a023ab58cca0ad752afe656bb3c17256a9f6e9bf19fdd5a38fc82bbe872c5539edb609ef4f79c203ebb140f2e583cb2ad15b4aa5b655016a8449277dbd477ef2c8d6c017db738b18deb4a427d1923ce3ff262735779a418f20a282df920147beabe421ee5319d0568");
+        AesXts::new(&KEY1, &KEY2).encrypt_sector(0, &mut data);
+        assert_eq!(&data[..], &expected[..]);
+    }
+
+    #[test]
+    fn sector_number() {
+        let mut data = plaintext();
+        AesXts::new(&KEY1, &KEY2).encrypt_sector(0x0123456789, &mut data);
+        assert_eq!(data[..32], hex("f0d6a76e2d21d9695a3c36ff5e8c557e4240e2db88bb82c2cd8cca303e2348fc")[..]);
+        assert_eq!(data[480..], hex("4f586d04f6b357d7d89034982c1fa1fc55a0886ef0bdec2e156c0f10f751ed21")[..]);
+    }
+}
@@ -0,0 +1,30 @@
+//! Writing random bytes to a socket using the `io::Read` implementation.
+#![cfg(feature = "std")]
+
+extern crate aesrng;
+
+use std::io::{self, Read};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use aesrng::AesRng;
+use aesrng::rand_core::SeedableRng;
+
+const LEN: u64 = 1 << 20;
+
+#[test]
+fn tcp_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let writer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut rng = AesRng::from_seed([1; 32]);
+        io::copy(&mut (&mut rng).take(LEN), &mut stream).unwrap()
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let received = io::copy(&mut stream, &mut io::sink()).unwrap();
+    assert_eq!(writer.join().unwrap(), LEN);
+    assert_eq!(received, LEN);
+}